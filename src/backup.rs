@@ -0,0 +1,290 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A backup of a config, named `<config>.<id>.bak` and stored next to it.
+#[derive(Debug, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// The UTC time the backup was made, as `YYYYMMDDTHHMMSSZ`, followed by `-<n>` if several
+    /// backups were made within the same second
+    pub id: String,
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into place, so that
+/// `path` is never left partially written. The permissions of an existing file are kept, since
+/// configs are often made read-only to stop the game from overwriting them.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| replace(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+/// Renames `temp_path` over `path`, then gives it the permissions `path` had. Windows refuses to
+/// replace a read-only file, so its read-only attribute is cleared for the rename, and set again
+/// if the rename fails.
+fn replace(temp_path: &Path, path: &Path) -> io::Result<()> {
+    let permissions = match fs::metadata(path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    #[cfg(windows)]
+    if let Some(permissions) = permissions.as_ref().filter(|p| p.readonly()) {
+        let mut writable = permissions.clone();
+        // Only clears the read-only attribute on Windows
+        #[allow(clippy::permissions_set_readonly_false)]
+        writable.set_readonly(false);
+        fs::set_permissions(path, writable)?;
+    }
+
+    match (fs::rename(temp_path, path), permissions) {
+        (Ok(()), Some(permissions)) => fs::set_permissions(path, permissions),
+        (Err(e), Some(permissions)) => {
+            let _ = fs::set_permissions(path, permissions);
+            Err(e)
+        }
+        (result, None) => result,
+    }
+}
+
+/// Copies `path` to a new timestamped backup, then removes the oldest backups so that at most
+/// `keep` remain.
+pub fn create(path: &Path, keep: usize) -> io::Result<Backup> {
+    let timestamp = timestamp(SystemTime::now());
+
+    // Backups made within the same second are numbered after the newest one
+    let next = list(path)?
+        .iter()
+        .filter_map(|backup| match split_id(&backup.id) {
+            (t, Some(n)) if t == timestamp => Some(n + 1),
+            _ => None,
+        })
+        .max();
+    let id = match next {
+        Some(n) => format!("{}-{}", timestamp, n),
+        None => timestamp,
+    };
+    let backup = Backup {
+        path: backup_path(path, &id),
+        id,
+    };
+
+    fs::copy(path, &backup.path)?;
+
+    let backups = list(path)?;
+    for old in &backups[..backups.len().saturating_sub(keep)] {
+        fs::remove_file(&old.path)?;
+    }
+
+    Ok(backup)
+}
+
+/// Atomically replaces `path` with the contents of `backup`. What it replaces is backed up
+/// first, without removing any other backups, so that the restore can itself be undone.
+/// Returns that backup, or `None` if `path` did not exist.
+pub fn restore(path: &Path, backup: &Backup) -> io::Result<Option<Backup>> {
+    let contents = fs::read_to_string(&backup.path)?;
+
+    let replaced = if path.exists() {
+        Some(create(path, list(path)?.len() + 1)?)
+    } else {
+        None
+    };
+    write_atomic(path, &contents)?;
+
+    Ok(replaced)
+}
+
+/// Lists the backups of `path`, from oldest to newest.
+pub fn list(path: &Path) -> io::Result<Vec<Backup>> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let prefix = format!("{}.", file_name);
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let id = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(&prefix))
+            .and_then(|name| name.strip_suffix(".bak"))
+            .filter(|id| is_backup_id(id));
+
+        if let Some(id) = id {
+            backups.push(Backup {
+                path: backup_path(path, id),
+                id: id.to_owned(),
+            });
+        }
+    }
+
+    backups.sort_by(|a, b| compare_ids(&a.id, &b.id));
+
+    Ok(backups)
+}
+
+fn backup_path(path: &Path, id: &str) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.bak", file_name, id))
+}
+
+fn is_backup_id(id: &str) -> bool {
+    let (timestamp, n) = split_id(id);
+    let bytes = timestamp.as_bytes();
+
+    bytes.len() == 16
+        && bytes[8] == b'T'
+        && bytes[15] == b'Z'
+        && bytes[..8]
+            .iter()
+            .chain(&bytes[9..15])
+            .all(u8::is_ascii_digit)
+        && n.is_some()
+}
+
+/// Splits an id into its timestamp and its sequence number within that second.
+fn split_id(id: &str) -> (&str, Option<u32>) {
+    match id.find('-') {
+        Some(index) => (&id[..index], id[index + 1..].parse().ok()),
+        None => (id, Some(0)),
+    }
+}
+
+fn compare_ids(a: &str, b: &str) -> std::cmp::Ordering {
+    split_id(a).cmp(&split_id(b))
+}
+
+/// Formats `time` as a UTC timestamp like `20200719T143005Z`.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Converts days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("csgocfg-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(UNIX_EPOCH), "19700101T000000Z");
+        assert_eq!(
+            timestamp(UNIX_EPOCH + Duration::from_secs(1_595_169_005)),
+            "20200719T143005Z"
+        );
+        assert_eq!(
+            timestamp(UNIX_EPOCH + Duration::from_secs(951_825_600)),
+            "20000229T120000Z"
+        );
+    }
+
+    #[test]
+    fn test_write_atomic() -> io::Result<()> {
+        let dir = temp_dir("write-atomic");
+        let path = dir.join("autoexec.cfg");
+
+        fs::write(&path, "volume 0.5\n")?;
+        write_atomic(&path, "volume 1\n")?;
+
+        assert_eq!(fs::read_to_string(&path)?, "volume 1\n");
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+        fs::remove_dir_all(dir)
+    }
+
+    #[test]
+    fn test_write_atomic_keeps_permissions() -> io::Result<()> {
+        let dir = temp_dir("write-atomic-permissions");
+        let path = dir.join("config.cfg");
+
+        fs::write(&path, "volume 0.5\n")?;
+        let mut permissions = fs::metadata(&path)?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions)?;
+        write_atomic(&path, "volume 1\n")?;
+
+        assert_eq!(fs::read_to_string(&path)?, "volume 1\n");
+        assert!(fs::metadata(&path)?.permissions().readonly());
+
+        write_atomic(&dir.join("new.cfg"), "volume 1\n")?;
+        assert!(!fs::metadata(dir.join("new.cfg"))?.permissions().readonly());
+
+        fs::remove_dir_all(dir)
+    }
+
+    #[test]
+    fn test_backups_are_pruned() -> io::Result<()> {
+        let dir = temp_dir("backups");
+        let path = dir.join("autoexec.cfg");
+
+        for n in 0..4 {
+            fs::write(&path, format!("volume {}\n", n))?;
+            create(&path, 3)?;
+        }
+        fs::write(dir.join("autoexec.cfg.notes.bak"), "")?;
+
+        let backups = list(&path)?;
+        assert_eq!(backups.len(), 3);
+        assert_eq!(fs::read_to_string(&backups[0].path)?, "volume 1\n");
+        assert_eq!(fs::read_to_string(&backups[2].path)?, "volume 3\n");
+
+        let replaced = restore(&path, &backups[0])?.unwrap();
+        assert_eq!(fs::read_to_string(&path)?, "volume 1\n");
+        assert_eq!(fs::read_to_string(&replaced.path)?, "volume 3\n");
+        assert_eq!(list(&path)?.len(), 4);
+
+        fs::remove_file(&path)?;
+        assert_eq!(restore(&path, &replaced)?, None);
+        assert_eq!(fs::read_to_string(&path)?, "volume 3\n");
+
+        fs::remove_dir_all(dir)
+    }
+}
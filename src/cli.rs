@@ -0,0 +1,717 @@
+use crate::{
+    format::Format,
+    lint::{self, Level},
+    output::{ColorChoice, Verbosity},
+    Error, LintOptions, PatchOptions, ValidateOptions, STANDARD_STREAM,
+};
+use std::path::{Path, PathBuf};
+
+/// An option, accepted either by a single subcommand or by all of them.
+#[derive(Debug)]
+struct Opt {
+    long: &'static str,
+    short: Option<&'static str>,
+    /// The name of the value the option takes, if any
+    value: Option<&'static str>,
+    about: &'static str,
+}
+
+/// A subcommand, described in enough detail to print its help.
+#[derive(Debug)]
+pub struct Subcommand {
+    pub name: &'static str,
+    /// The positional arguments, as shown in the usage line
+    args: &'static str,
+    options: &'static [Opt],
+    about: &'static str,
+}
+
+const GLOBAL_OPTIONS: &[Opt] = &[
+    Opt {
+        long: "--quiet",
+        short: Some("-q"),
+        value: None,
+        about: "Only prints output and errors",
+    },
+    Opt {
+        long: "--verbose",
+        short: Some("-v"),
+        value: None,
+        about: "Also prints what is being done",
+    },
+    Opt {
+        long: "--color",
+        short: None,
+        value: Some("when"),
+        about: "Colors the output: auto, always or never (default auto)",
+    },
+    Opt {
+        long: "--help",
+        short: Some("-h"),
+        value: None,
+        about: "Prints help",
+    },
+    Opt {
+        long: "--version",
+        short: Some("-V"),
+        value: None,
+        about: "Prints the version",
+    },
+];
+
+const SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "patch",
+        args: "<target> <patch>...",
+        options: &[
+            Opt {
+                long: "--explain",
+                short: None,
+                value: None,
+                about: "Prints which patch set the final value of each item to stderr",
+            },
+            Opt {
+                long: "--dry-run",
+                short: None,
+                value: None,
+                about: "Prints a diff of the changes instead of writing them",
+            },
+            Opt {
+                long: "--check",
+                short: None,
+                value: None,
+                about: "Fails if the patch would modify the target",
+            },
+            Opt {
+                long: "--backups",
+                short: None,
+                value: Some("count"),
+                about: "Number of backups of the output to keep (default 5)",
+            },
+            Opt {
+                long: "--output",
+                short: Some("-o"),
+                value: Some("file"),
+                about: "Writes the result to a file, or stdout for `-`, instead of the target",
+            },
+        ],
+        about: "Applies the given patches onto the target, in order",
+    },
+    Subcommand {
+        name: "validate",
+        args: "<file>",
+        options: &[
+            Opt {
+                long: "--deny-warnings",
+                short: None,
+                value: None,
+                about: "Fails if there are any warnings, not just errors",
+            },
+            Opt {
+                long: "--format",
+                short: None,
+                value: Some("format"),
+                about: "Prints diagnostics as human, json or sarif (default human)",
+            },
+            Opt {
+                long: "--root",
+                short: None,
+                value: Some("dir"),
+                about: "Resolves `exec` targets in a directory (default that of the file)",
+            },
+        ],
+        about: "Validates a config file and the configs it executes",
+    },
+    Subcommand {
+        name: "lint",
+        args: "<file>",
+        options: &[
+            Opt {
+                long: "--allow",
+                short: Some("-A"),
+                value: Some("rule"),
+                about: "Disables a rule, or every rule for `all`",
+            },
+            Opt {
+                long: "--warn",
+                short: Some("-W"),
+                value: Some("rule"),
+                about: "Reports a rule as a warning",
+            },
+            Opt {
+                long: "--deny",
+                short: Some("-D"),
+                value: Some("rule"),
+                about: "Reports a rule as an error",
+            },
+            Opt {
+                long: "--deny-warnings",
+                short: None,
+                value: None,
+                about: "Fails if there are any warnings, not just errors",
+            },
+            Opt {
+                long: "--format",
+                short: None,
+                value: Some("format"),
+                about: "Prints diagnostics as human, json or sarif (default human)",
+            },
+            Opt {
+                long: "--list",
+                short: None,
+                value: None,
+                about: "Lists the rules and their default levels instead",
+            },
+        ],
+        about: "Checks a config for likely mistakes",
+    },
+    Subcommand {
+        name: "restore",
+        args: "<target>",
+        options: &[
+            Opt {
+                long: "--list",
+                short: None,
+                value: None,
+                about: "Lists the backups of the target instead",
+            },
+            Opt {
+                long: "--to",
+                short: None,
+                value: Some("id"),
+                about: "Restores the backup with the given id instead",
+            },
+        ],
+        about: "Restores the latest backup of the target",
+    },
+    Subcommand {
+        name: "diff",
+        args: "<old> <new>",
+        options: &[],
+        about: "Prints a patch which turns the old config into the new one",
+    },
+    Subcommand {
+        name: "merge",
+        args: "<base> <ours> <theirs>",
+        options: &[],
+        about: "Prints the three-way merge of two configs with a common base",
+    },
+    Subcommand {
+        name: "merge-driver",
+        args: "<base> <ours> <theirs>",
+        options: &[],
+        about: "Merges into ours, for use as a git merge driver",
+    },
+    Subcommand {
+        name: "help",
+        args: "[<command>]",
+        options: &[],
+        about: "Prints help for the tool or one of its commands",
+    },
+];
+
+#[derive(Debug)]
+pub struct Cli {
+    pub verbosity: Verbosity,
+    pub color: ColorChoice,
+    pub command: Command,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Patch {
+        target: PathBuf,
+        patches: Vec<PathBuf>,
+        options: PatchOptions,
+    },
+    Validate {
+        target: PathBuf,
+        options: ValidateOptions,
+    },
+    Lint {
+        target: PathBuf,
+        options: LintOptions,
+    },
+    LintRules,
+    Restore {
+        target: PathBuf,
+        list: bool,
+        to: Option<String>,
+    },
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    Merge {
+        base: PathBuf,
+        ours: PathBuf,
+        theirs: PathBuf,
+    },
+    MergeDriver {
+        base: PathBuf,
+        ours: PathBuf,
+        theirs: PathBuf,
+    },
+    /// Print the help of a subcommand, or of the whole tool if there is none
+    Help(Option<&'static Subcommand>),
+    Version,
+}
+
+/// The arguments given to a subcommand, sorted into positional arguments and options.
+struct Matches {
+    args: std::vec::IntoIter<String>,
+    flags: Vec<&'static str>,
+    /// The values of options in the order they were given, since some may be repeated
+    values: Vec<(&'static str, String)>,
+    /// Whether an argument has already claimed stdin
+    stdin: bool,
+}
+
+impl Matches {
+    fn flag(&self, long: &str) -> bool {
+        self.flags.contains(&long)
+    }
+
+    /// Returns the last value given for an option.
+    fn value(&mut self, long: &str) -> Option<String> {
+        let index = self.values.iter().rposition(|(name, _)| *name == long)?;
+        Some(self.values.remove(index).1)
+    }
+
+    fn arg(&mut self, name: &'static str) -> Result<String, Error> {
+        self.args.next().ok_or(Error::MissingArgument(name))
+    }
+
+    /// Takes the next argument as the path of an input, which may be `-` for stdin.
+    fn input(&mut self, name: &'static str) -> Result<PathBuf, Error> {
+        let path = self.arg(name)?;
+        self.input_path(path)
+    }
+
+    fn input_path(&mut self, path: String) -> Result<PathBuf, Error> {
+        if path == STANDARD_STREAM {
+            if self.stdin {
+                return Err(Error::StdinReadTwice);
+            }
+            self.stdin = true;
+            return Ok(PathBuf::from(path));
+        }
+
+        existing_path(path)
+    }
+
+    /// Takes the next argument as the path of a file, which must exist.
+    fn file(&mut self, name: &'static str) -> Result<PathBuf, Error> {
+        match self.arg(name)? {
+            path if path == STANDARD_STREAM => Err(Error::StdinNotSupported(name)),
+            path => existing_path(path),
+        }
+    }
+
+    /// Takes the next argument as the path of a file which need not exist, as long as the
+    /// directory it would be in does.
+    fn target(&mut self, name: &'static str) -> Result<PathBuf, Error> {
+        let path = self.arg(name)?;
+        if path == STANDARD_STREAM {
+            return Err(Error::StdinNotSupported(name));
+        }
+        if Path::new(&path).exists() {
+            return existing_path(path);
+        }
+
+        let dir = match Path::new(&path).parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        match (dir.canonicalize(), Path::new(&path).file_name()) {
+            (Ok(dir), Some(file_name)) => Ok(dir.join(file_name)),
+            _ => Err(Error::FileNotFound(path)),
+        }
+    }
+
+    /// Fails if any positional arguments are left over.
+    fn finish(mut self) -> Result<(), Error> {
+        match self.args.next() {
+            Some(arg) => Err(Error::UnexpectedArgument(arg)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses the command line arguments, without the program name. Global options may be given
+/// before or after the subcommand, while the options of a subcommand must follow it.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, Error> {
+    let mut args = args.into_iter();
+    let mut subcommand = None;
+    let mut matches = Matches {
+        args: Vec::new().into_iter(),
+        flags: Vec::new(),
+        values: Vec::new(),
+        stdin: false,
+    };
+    let mut positional = Vec::new();
+    let mut options_done = false;
+
+    while let Some(arg) = args.next() {
+        if arg == "--" && !options_done {
+            options_done = true;
+            continue;
+        }
+
+        if options_done || arg == "-" || !arg.starts_with('-') {
+            match subcommand {
+                None => subcommand = Some(find_subcommand(&arg)?),
+                Some(_) => positional.push(arg),
+            }
+            continue;
+        }
+
+        // Long options may take their value as `--name=value`
+        let (name, inline_value) = match arg.find('=') {
+            Some(index) if arg.starts_with("--") => (&arg[..index], Some(&arg[index + 1..])),
+            _ => (&arg[..], None),
+        };
+        let local: &[Opt] = subcommand.map_or(&[], |s: &Subcommand| s.options);
+        let opt = GLOBAL_OPTIONS
+            .iter()
+            .chain(local)
+            .find(|opt| opt.long == name || opt.short == Some(name))
+            .ok_or_else(|| Error::UnrecognizedOption(arg.clone()))?;
+
+        match (opt.value, inline_value) {
+            (Some(_), Some(value)) => {
+                matches.values.push((opt.long, value.to_owned()));
+            }
+            (Some(name), None) => {
+                let value = args.next().ok_or(Error::MissingArgument(name))?;
+                matches.values.push((opt.long, value));
+            }
+            (None, Some(_)) => return Err(Error::UnrecognizedOption(arg)),
+            (None, None) => matches.flags.push(opt.long),
+        }
+    }
+    matches.args = positional.into_iter();
+
+    // When both are given, the last of `--quiet` and `--verbose` wins
+    let verbosity = match matches
+        .flags
+        .iter()
+        .rev()
+        .find(|flag| **flag == "--quiet" || **flag == "--verbose")
+    {
+        Some(&"--quiet") => Verbosity::Quiet,
+        Some(_) => Verbosity::Verbose,
+        None => Verbosity::Normal,
+    };
+    let color = match matches.value("--color").as_deref() {
+        None | Some("auto") => ColorChoice::Auto,
+        Some("always") => ColorChoice::Always,
+        Some("never") => ColorChoice::Never,
+        Some(other) => return Err(Error::InvalidOptionValue("--color", other.to_owned())),
+    };
+
+    let command = if matches.flag("--help") {
+        Command::Help(subcommand)
+    } else if matches.flag("--version") {
+        Command::Version
+    } else {
+        let subcommand = subcommand.ok_or(Error::NoCommandSpecified)?;
+        parse_subcommand(subcommand, matches)?
+    };
+
+    Ok(Cli {
+        verbosity,
+        color,
+        command,
+    })
+}
+
+fn parse_subcommand(subcommand: &Subcommand, mut matches: Matches) -> Result<Command, Error> {
+    let command = match subcommand.name {
+        "patch" => {
+            let mut options = PatchOptions {
+                dry_run: matches.flag("--dry-run"),
+                check: matches.flag("--check"),
+                explain: matches.flag("--explain"),
+                ..PatchOptions::default()
+            };
+            if let Some(count) = matches.value("--backups") {
+                options.backups = count
+                    .parse()
+                    .map_err(|_| Error::InvalidOptionValue("--backups", count))?;
+            }
+            options.output = matches.value("--output").map(output_path);
+
+            let target = matches.input("target")?;
+            let mut patches = vec![matches.input("patch")?];
+            while let Some(path) = matches.args.next() {
+                let patch = matches.input_path(path)?;
+                patches.push(patch);
+            }
+
+            Command::Patch {
+                target,
+                patches,
+                options,
+            }
+        }
+        "validate" => Command::Validate {
+            options: ValidateOptions {
+                deny_warnings: matches.flag("--deny-warnings"),
+                format: format(&mut matches)?,
+                root: matches.value("--root").map(existing_path).transpose()?,
+            },
+            target: matches.input("file")?,
+        },
+        "lint" if matches.flag("--list") => Command::LintRules,
+        "lint" => {
+            let mut options = LintOptions {
+                deny_warnings: matches.flag("--deny-warnings"),
+                format: format(&mut matches)?,
+                ..LintOptions::default()
+            };
+
+            // Later options take precedence, so `-A all -W duplicate-bind` enables a single rule
+            for (option, name) in &matches.values {
+                let level = match *option {
+                    "--allow" => Level::Allow,
+                    "--warn" => Level::Warn,
+                    "--deny" => Level::Deny,
+                    _ => continue,
+                };
+                if name == "all" {
+                    for rule in lint::RULES {
+                        options.levels.insert(rule.name, level);
+                    }
+                } else {
+                    let rule = lint::find(name).ok_or_else(|| Error::UnknownRule(name.clone()))?;
+                    options.levels.insert(rule.name, level);
+                }
+            }
+
+            Command::Lint {
+                target: matches.input("file")?,
+                options,
+            }
+        }
+        "restore" => Command::Restore {
+            list: matches.flag("--list"),
+            to: matches.value("--to"),
+            target: matches.target("target")?,
+        },
+        "diff" => Command::Diff {
+            old: matches.input("old")?,
+            new: matches.input("new")?,
+        },
+        "merge" => Command::Merge {
+            base: matches.input("base")?,
+            ours: matches.input("ours")?,
+            theirs: matches.input("theirs")?,
+        },
+        "merge-driver" => Command::MergeDriver {
+            base: matches.input("base")?,
+            ours: matches.file("ours")?,
+            theirs: matches.input("theirs")?,
+        },
+        "help" => match matches.args.next() {
+            Some(name) => Command::Help(Some(find_subcommand(&name)?)),
+            None => Command::Help(None),
+        },
+        name => unreachable!("subcommand `{}` is not handled", name),
+    };
+
+    matches.finish()?;
+
+    Ok(command)
+}
+
+fn format(matches: &mut Matches) -> Result<Format, Error> {
+    match matches.value("--format").as_deref() {
+        None | Some("human") => Ok(Format::Human),
+        Some("json") => Ok(Format::Json),
+        Some("sarif") => Ok(Format::Sarif),
+        Some(other) => Err(Error::InvalidOptionValue("--format", other.to_owned())),
+    }
+}
+
+fn find_subcommand(name: &str) -> Result<&'static Subcommand, Error> {
+    SUBCOMMANDS
+        .iter()
+        .find(|subcommand| subcommand.name == name)
+        .ok_or_else(|| Error::UnrecognizedCommand(name.to_owned()))
+}
+
+fn existing_path(path: String) -> Result<PathBuf, Error> {
+    Path::new(&path)
+        .canonicalize()
+        .map_err(|_| Error::FileNotFound(path))
+}
+
+/// Resolves the path to write to, which need not exist yet.
+fn output_path(path: String) -> PathBuf {
+    if path == STANDARD_STREAM {
+        return PathBuf::from(path);
+    }
+
+    Path::new(&path)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Returns the help of `subcommand`, or of the whole tool if there is none.
+pub fn help(subcommand: Option<&Subcommand>) -> String {
+    match subcommand {
+        Some(subcommand) => format!(
+            "{}\n\nusage: csgocfg {} [options] {}\n\nOptions:\n{}",
+            subcommand.about,
+            subcommand.name,
+            subcommand.args,
+            options_help(subcommand.options.iter().chain(GLOBAL_OPTIONS))
+        ),
+        None => {
+            let commands: Vec<_> = SUBCOMMANDS
+                .iter()
+                .map(|subcommand| {
+                    let usage = format!("{} {}", subcommand.name, subcommand.args);
+                    (usage, subcommand.about)
+                })
+                .collect();
+
+            format!(
+                "usage: csgocfg [options] <command> [<args>]\n\nCommands:\n{}\n\nOptions:\n{}\n\n\
+                 A config may be given as `-` to read it from stdin.\n\
+                 Run `csgocfg help <command>` for the options of a command.",
+                columns(&commands),
+                options_help(GLOBAL_OPTIONS.iter())
+            )
+        }
+    }
+}
+
+fn options_help<'a>(options: impl Iterator<Item = &'a Opt>) -> String {
+    let rows: Vec<_> = options
+        .map(|opt| {
+            let mut name = match opt.short {
+                Some(short) => format!("{}, {}", short, opt.long),
+                None => format!("    {}", opt.long),
+            };
+            if let Some(value) = opt.value {
+                name.push_str(&format!(" <{}>", value));
+            }
+            (name, opt.about)
+        })
+        .collect();
+
+    columns(&rows)
+}
+
+/// Lays out rows of names and descriptions in two aligned columns.
+fn columns(rows: &[(String, &str)]) -> String {
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    let lines: Vec<_> = rows
+        .iter()
+        .map(|(name, about)| format!("    {:width$}   {}", name, about, width = width))
+        .collect();
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Cli, Error> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_global_options() -> Result<(), Error> {
+        let cli = parse_args(&["--quiet", "help", "--color=never"])?;
+        assert_eq!(cli.verbosity, Verbosity::Quiet);
+        assert_eq!(cli.color, ColorChoice::Never);
+
+        let cli = parse_args(&["-q", "-v", "--color", "always", "--version"])?;
+        assert_eq!(cli.verbosity, Verbosity::Verbose);
+        assert_eq!(cli.color, ColorChoice::Always);
+        assert!(matches!(cli.command, Command::Version));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_help() -> Result<(), Error> {
+        assert!(matches!(
+            parse_args(&["restore", "--help"])?.command,
+            Command::Help(Some(Subcommand {
+                name: "restore",
+                ..
+            }))
+        ));
+        assert!(matches!(
+            parse_args(&["help", "diff"])?.command,
+            Command::Help(Some(Subcommand { name: "diff", .. }))
+        ));
+        assert!(matches!(parse_args(&["-h"])?.command, Command::Help(None)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(parse_args(&[]), Err(Error::NoCommandSpecified)));
+        assert!(matches!(
+            parse_args(&["frobnicate"]),
+            Err(Error::UnrecognizedCommand(command)) if command == "frobnicate"
+        ));
+        assert!(matches!(
+            parse_args(&["--dry-run", "patch"]),
+            Err(Error::UnrecognizedOption(option)) if option == "--dry-run"
+        ));
+        assert!(matches!(
+            parse_args(&["help", "--color", "sometimes"]),
+            Err(Error::InvalidOptionValue("--color", value)) if value == "sometimes"
+        ));
+        assert!(matches!(
+            parse_args(&["restore", "--to"]),
+            Err(Error::MissingArgument("id"))
+        ));
+        assert!(matches!(
+            parse_args(&["help", "diff", "merge"]),
+            Err(Error::UnexpectedArgument(arg)) if arg == "merge"
+        ));
+    }
+
+    #[test]
+    fn test_stdin() -> Result<(), Error> {
+        match parse_args(&["patch", "-", ".", "-o", "-"])?.command {
+            Command::Patch {
+                target, options, ..
+            } => {
+                assert_eq!(target, Path::new("-"));
+                assert_eq!(options.output, Some(PathBuf::from("-")));
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+
+        assert!(matches!(
+            parse_args(&["diff", "-", "-"]),
+            Err(Error::StdinReadTwice)
+        ));
+        assert!(matches!(
+            parse_args(&["restore", "-"]),
+            Err(Error::StdinNotSupported("target"))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_help_lists_options() {
+        let help = help(find_subcommand("patch").ok());
+
+        assert!(help.starts_with("Applies the given patches onto the target, in order\n"));
+        assert!(help.contains("usage: csgocfg patch [options] <target> <patch>...\n"));
+        assert!(help.contains("        --backups <count>   Number of backups"));
+        assert!(help.contains("    -q, --quiet         "));
+    }
+}
@@ -1,18 +1,140 @@
+use crate::keys;
 use std::fmt::Display;
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum ConfigItem {
-    Command(String),
-    Bind(String, String),
-    Cvar(String, String),
+    Command { name: String, args: Vec<Arg> },
+    Bind(Arg, Arg),
+    Cvar(String, Arg),
+    Alias(Arg, Vec<ConfigItem>),
+}
+
+/// An argument to a command, which remembers whether it was quoted so it can be written back
+/// the way it was read.
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+pub struct Arg {
+    pub value: String,
+    pub quoted: bool,
+}
+
+impl Arg {
+    pub fn quoted(value: &str) -> Self {
+        Arg {
+            value: value.to_owned(),
+            quoted: true,
+        }
+    }
+
+    pub fn unquoted(value: &str) -> Self {
+        Arg {
+            value: value.to_owned(),
+            quoted: false,
+        }
+    }
+}
+
+/// The setting a `ConfigItem` controls, regardless of the value it is set to.
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ConfigKey {
+    Command(String, Vec<String>),
+    Bind(String),
+    Cvar(String),
+    Alias(String),
+}
+
+impl ConfigKey {
+    /// Returns the key of the bind for the given key name, which is the same for every spelling
+    /// of the name the game accepts.
+    pub fn bind(name: &str) -> Self {
+        ConfigKey::Bind(canonical_key(name))
+    }
+
+    /// Returns the key of the given cvar, whose name is matched regardless of case like the game
+    /// does.
+    pub fn cvar(name: &str) -> Self {
+        ConfigKey::Cvar(name.to_ascii_lowercase())
+    }
+}
+
+/// Returns the name the game knows a key by, or the name in lowercase if it is unknown.
+fn canonical_key(name: &str) -> String {
+    keys::canonical(name).map_or_else(|| name.to_ascii_lowercase(), str::to_owned)
+}
+
+impl ConfigItem {
+    /// Returns the key identifying this item, so that two items setting the same
+    /// cvar or binding the same key compare equal regardless of their values.
+    pub fn key(&self) -> ConfigKey {
+        match self {
+            // Commands have no value separate from their arguments, so they are identified by all
+            // of them
+            ConfigItem::Command { name, args } => ConfigKey::Command(
+                name.clone(),
+                args.iter().map(|arg| arg.value.clone()).collect(),
+            ),
+            ConfigItem::Bind(key, _) => ConfigKey::bind(&key.value),
+            ConfigItem::Cvar(cvar, _) => ConfigKey::cvar(cvar),
+            ConfigItem::Alias(name, _) => ConfigKey::Alias(name.value.clone()),
+        }
+    }
+
+    /// Returns a copy of this item with every argument quoted, bind keys in their canonical
+    /// spelling and cvar names in lowercase, so that items which only differ in formatting
+    /// compare equal.
+    pub fn normalized(&self) -> ConfigItem {
+        let quoted = |arg: &Arg| Arg::quoted(&arg.value);
+
+        match self {
+            ConfigItem::Command { name, args } => ConfigItem::Command {
+                name: name.clone(),
+                args: args.iter().map(quoted).collect(),
+            },
+            ConfigItem::Bind(key, bind) => {
+                ConfigItem::Bind(Arg::quoted(&canonical_key(&key.value)), quoted(bind))
+            }
+            ConfigItem::Cvar(cvar, val) => ConfigItem::Cvar(cvar.to_ascii_lowercase(), quoted(val)),
+            ConfigItem::Alias(name, body) => ConfigItem::Alias(
+                quoted(name),
+                body.iter().map(ConfigItem::normalized).collect(),
+            ),
+        }
+    }
 }
 
 impl Display for ConfigItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ConfigItem::Command(cmd) => write!(f, "{}", cmd),
-            ConfigItem::Bind(key, bind) => write!(f, "bind \"{}\" \"{}\"", key, bind),
-            ConfigItem::Cvar(cvar, val) => write!(f, "{} \"{}\"", cvar, val),
+            ConfigItem::Command { name, args } => {
+                write!(f, "{}", name)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                Ok(())
+            }
+            ConfigItem::Bind(key, bind) => write!(f, "bind {} {}", key, bind),
+            ConfigItem::Cvar(cvar, val) => write!(f, "{} {}", cvar, val),
+            // The body of an alias is always written back quoted, since it may contain several
+            // statements
+            ConfigItem::Alias(name, body) => {
+                write!(f, "alias {} \"", name)?;
+                for (index, item) in body.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "\"")
+            }
+        }
+    }
+}
+
+impl Display for Arg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.quoted {
+            write!(f, "\"{}\"", self.value)
+        } else {
+            write!(f, "{}", self.value)
         }
     }
 }
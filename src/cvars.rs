@@ -0,0 +1,196 @@
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+/// The embedded catalog of known cvars, see the file for its format.
+const CATALOG: &str = include_str!("cvars.txt");
+
+/// A cvar known to the game, along with the values it accepts.
+#[derive(Debug, PartialEq)]
+pub struct Cvar {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub default: &'static str,
+    pub flags: Vec<Flag>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Bool,
+    Int,
+    Float,
+    String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flag {
+    /// Saved to `config.cfg` by the game
+    Archive,
+    /// Only takes effect while `sv_cheats` is enabled
+    Cheat,
+    /// Only available in development builds of the game
+    DevOnly,
+    /// Controlled by the server
+    Replicated,
+}
+
+/// Why a value is not accepted by a cvar.
+#[derive(Debug, PartialEq)]
+pub enum Invalid {
+    TypeMismatch,
+    OutOfRange,
+}
+
+impl Cvar {
+    pub fn has_flag(&self, flag: Flag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Checks that `value` has the type of the cvar and lies within its range.
+    pub fn check(&self, value: &str) -> Result<(), Invalid> {
+        let number = match self.kind {
+            Kind::String => return Ok(()),
+            Kind::Bool | Kind::Int => value.parse::<i64>().ok().map(|n| n as f64),
+            Kind::Float => value.parse::<f64>().ok().filter(|n| n.is_finite()),
+        };
+        let number = number.ok_or(Invalid::TypeMismatch)?;
+
+        if self.min.is_some_and(|min| number < min) || self.max.is_some_and(|max| number > max) {
+            return Err(Invalid::OutOfRange);
+        }
+
+        Ok(())
+    }
+
+    /// Describes the range of the cvar, like `between 0 and 1`.
+    pub fn range(&self) -> String {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => format!("between {} and {}", min, max),
+            (Some(min), None) => format!("at least {}", min),
+            (None, Some(max)) => format!("at most {}", max),
+            (None, None) => "any value".to_owned(),
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Bool => write!(f, "a boolean (0 or 1)"),
+            Kind::Int => write!(f, "an integer"),
+            Kind::Float => write!(f, "a number"),
+            Kind::String => write!(f, "a string"),
+        }
+    }
+}
+
+/// Looks up a cvar by name, ignoring case like the game does.
+pub fn lookup(name: &str) -> Option<&'static Cvar> {
+    static CVARS: OnceLock<HashMap<&'static str, Cvar>> = OnceLock::new();
+
+    let cvars = CVARS.get_or_init(|| {
+        catalog_lines()
+            .map(|line| {
+                let cvar = parse_entry(line)
+                    .unwrap_or_else(|| panic!("invalid entry in the cvar catalog: {}", line));
+                (cvar.name, cvar)
+            })
+            .collect()
+    });
+    cvars.get(name.to_ascii_lowercase().as_str())
+}
+
+fn catalog_lines() -> impl Iterator<Item = &'static str> {
+    CATALOG
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn parse_entry(line: &'static str) -> Option<Cvar> {
+    let mut fields = line.split_whitespace();
+    let mut next = || fields.next();
+    let bound = |field: &str| match field {
+        "-" => Some(None),
+        _ => field.parse().ok().map(Some),
+    };
+
+    let name = next()?;
+    let kind = match next()? {
+        "bool" => Kind::Bool,
+        "int" => Kind::Int,
+        "float" => Kind::Float,
+        "string" => Kind::String,
+        _ => return None,
+    };
+    let min = bound(next()?)?;
+    let max = bound(next()?)?;
+    let default = match next()? {
+        "\"\"" => "",
+        default => default,
+    };
+    let flags = match next()? {
+        "-" => Vec::new(),
+        flags => flags
+            .split(',')
+            .map(|flag| match flag {
+                "archive" => Some(Flag::Archive),
+                "cheat" => Some(Flag::Cheat),
+                "devonly" => Some(Flag::DevOnly),
+                "replicated" => Some(Flag::Replicated),
+                _ => None,
+            })
+            .collect::<Option<_>>()?,
+    };
+
+    if next().is_some() {
+        return None;
+    }
+
+    Some(Cvar {
+        name,
+        kind,
+        min,
+        max,
+        default,
+        flags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_is_valid() {
+        let mut names = std::collections::HashSet::new();
+
+        for line in catalog_lines() {
+            let cvar = parse_entry(line).unwrap_or_else(|| panic!("invalid entry `{}`", line));
+            assert!(names.insert(cvar.name), "duplicate entry `{}`", cvar.name);
+            assert_eq!(cvar.name, cvar.name.to_ascii_lowercase());
+            assert_eq!(
+                cvar.check(cvar.default),
+                Ok(()),
+                "invalid default `{}`",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let sensitivity = lookup("Sensitivity").unwrap();
+        assert_eq!(sensitivity.check("1.8"), Ok(()));
+        assert_eq!(sensitivity.check("abc"), Err(Invalid::TypeMismatch));
+        assert_eq!(sensitivity.check("0"), Err(Invalid::OutOfRange));
+
+        let fps_max = lookup("fps_max").unwrap();
+        assert_eq!(fps_max.check("-5"), Err(Invalid::OutOfRange));
+        assert_eq!(fps_max.check("0.5"), Err(Invalid::TypeMismatch));
+        assert_eq!(fps_max.range(), "at least 0");
+
+        assert_eq!(lookup("name").unwrap().check("anything"), Ok(()));
+        assert_eq!(lookup("sv_chaets"), None);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{
+    output::{paint, Style},
+    parser::ParseError,
+};
+use std::{ops::Range, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found at a specific location in a config file.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The id of the rule which found the problem, like `duplicate-bind`
+    pub rule: Option<&'static str>,
+    pub message: String,
+    /// One-based line number
+    pub line_number: usize,
+    pub line: String,
+    /// The byte range of the offending input within `line`
+    pub span: Range<usize>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic for the line with the given zero-based index.
+    pub fn new(
+        severity: Severity,
+        message: String,
+        index: usize,
+        line: &str,
+        span: Range<usize>,
+    ) -> Self {
+        Diagnostic {
+            severity,
+            rule: None,
+            message,
+            line_number: index + 1,
+            line: line.to_owned(),
+            span,
+        }
+    }
+
+    /// Creates a diagnostic from an error on the line with the given zero-based index.
+    pub fn from_parse_error(error: &ParseError, index: usize, line: &str) -> Self {
+        Diagnostic::new(
+            Severity::Error,
+            error.to_string(),
+            index,
+            line,
+            error.span.clone(),
+        )
+        .with_rule(error.kind.id())
+    }
+
+    pub fn with_rule(self, rule: &'static str) -> Self {
+        Diagnostic {
+            rule: Some(rule),
+            ..self
+        }
+    }
+
+    /// One-based column of the start of the span, counted in characters.
+    pub fn column(&self) -> usize {
+        self.line[..self.span.start].chars().count() + 1
+    }
+
+    /// One-based column just past the end of the span, counted in characters.
+    pub fn end_column(&self) -> usize {
+        self.line[..self.span.end].chars().count() + 1
+    }
+
+    /// Renders the diagnostic along with a snippet of the offending line, with carets under
+    /// the span.
+    pub fn render(&self, path: &Path) -> String {
+        let gutter = " ".repeat(self.line_number.to_string().len());
+
+        // Keep tabs in the padding so the carets line up with the line above
+        let padding: String = self.line[..self.span.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(self.line[self.span.clone()].chars().count().max(1));
+
+        let bar = paint(" |", Style::Gutter);
+        let (label, style) = match self.severity {
+            Severity::Error => ("error", Style::Error),
+            Severity::Warning => ("warning", Style::Warning),
+        };
+        let label = match self.rule {
+            Some(rule) => format!("{}[{}]:", label, rule),
+            None => format!("{}:", label),
+        };
+
+        format!(
+            "{label} {message}\n{gutter}{arrow} {path}:{line_number}:{column}\n{gutter}{bar}\n\
+             {number}{bar} {line}\n{gutter}{bar} {padding}{carets}",
+            label = paint(&label, style),
+            message = self.message,
+            gutter = gutter,
+            arrow = paint("-->", Style::Gutter),
+            path = path.display(),
+            line_number = self.line_number,
+            column = self.column(),
+            number = paint(&self.line_number.to_string(), Style::Gutter),
+            bar = bar,
+            line = self.line,
+            padding = padding,
+            carets = paint(&carets, style),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            rule: None,
+            message: "invalid identifier `1quit`".to_owned(),
+            line_number: 12,
+            line: "\tbind w +forward; 1quit".to_owned(),
+            span: 18..23,
+        };
+
+        assert_eq!(diagnostic.column(), 19);
+        assert_eq!(
+            diagnostic.render(Path::new("autoexec.cfg")),
+            "error: invalid identifier `1quit`
+  --> autoexec.cfg:12:19
+   |
+12 | \tbind w +forward; 1quit
+   | \t                 ^^^^^"
+        );
+    }
+
+    #[test]
+    fn test_render_rule() {
+        let diagnostic = Diagnostic::new(
+            Severity::Warning,
+            "`f` is already bound on line 1".to_owned(),
+            1,
+            "bind f +use",
+            5..6,
+        )
+        .with_rule("duplicate-bind");
+
+        assert_eq!(
+            diagnostic.render(Path::new("autoexec.cfg")),
+            "warning[duplicate-bind]: `f` is already bound on line 1
+ --> autoexec.cfg:2:6
+  |
+2 | bind f +use
+  |      ^"
+        );
+    }
+}
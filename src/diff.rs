@@ -0,0 +1,159 @@
+/// Number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Returns a unified diff turning `old` into `new`, or an empty string if they have the same
+/// lines.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old, &new);
+
+    let hunks = hunks(&ops);
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for hunk in hunks {
+        let ops = &ops[hunk];
+        let (old_start, old_count) = range(ops, |op| match op {
+            Op::Equal(i, _) | Op::Delete(i) => Some(i),
+            Op::Insert(_) => None,
+        });
+        let (new_start, new_count) = range(ops, |op| match op {
+            Op::Equal(_, j) | Op::Insert(j) => Some(j),
+            Op::Delete(_) => None,
+        });
+
+        // Every hunk has context unless one side is an empty file, which GNU diff writes as `0,0`
+        let old_start = old_start.map_or(0, |i| i + 1);
+        let new_start = new_start.map_or(0, |j| j + 1);
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_count, new_start, new_count
+        ));
+
+        for op in ops {
+            match *op {
+                Op::Equal(i, _) => output.push_str(&format!(" {}\n", old[i])),
+                Op::Delete(i) => output.push_str(&format!("-{}\n", old[i])),
+                Op::Insert(j) => output.push_str(&format!("+{}\n", new[j])),
+            }
+        }
+    }
+
+    output
+}
+
+/// Finds the first line index of a hunk in one of the files, along with the number of lines it
+/// covers in that file.
+fn range(ops: &[Op], index: impl Fn(Op) -> Option<usize>) -> (Option<usize>, usize) {
+    let mut indices = ops.iter().filter_map(|&op| index(op));
+    let first = indices.next();
+    (first, first.map_or(0, |_| 1 + indices.count()))
+}
+
+/// Groups the changes of `ops` into ranges of ops, each with up to `CONTEXT` unchanged lines on
+/// either side.
+fn hunks(ops: &[Op]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+
+    for (index, op) in ops.iter().enumerate() {
+        if let Op::Equal(..) = op {
+            continue;
+        }
+
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + 1 + CONTEXT).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if last.end >= start => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    hunks
+}
+
+/// Computes a line-based edit script using the longest common subsequence of `old` and `new`.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(Op::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(Op::Delete(i));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(j));
+            j += 1;
+        }
+    }
+    ops.extend((i..old.len()).map(Op::Delete));
+    ops.extend((j..new.len()).map(Op::Insert));
+
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unchanged() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "a", "b"), "");
+    }
+
+    #[test]
+    fn test_changed_line() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        assert_eq!(
+            unified_diff(old, new, "a/cfg", "b/cfg"),
+            "--- a/cfg\n+++ b/cfg\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn test_separate_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "one\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n";
+        assert_eq!(
+            unified_diff(old, new, "a", "b"),
+            "--- a\n+++ b\n@@ -1,4 +1,4 @@\n-1\n+one\n 2\n 3\n 4\n@@ -8,3 +8,4 @@\n 8\n 9\n 10\n+11\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_side() {
+        assert_eq!(
+            unified_diff("", "a\n", "a", "b"),
+            "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+        assert_eq!(
+            unified_diff("a\n", "", "a", "b"),
+            "--- a\n+++ b\n@@ -1,1 +0,0 @@\n-a\n"
+        );
+    }
+}
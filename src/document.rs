@@ -0,0 +1,610 @@
+use crate::config::{ConfigItem, ConfigKey};
+use crate::parser::{self, ParseError, Statement};
+use std::{collections::HashMap, fmt::Display};
+
+/// A config file which keeps the original text of every line, so that it can be written back
+/// with its ordering, whitespace and comments intact.
+#[derive(Debug)]
+pub struct Document {
+    lines: Vec<Line>,
+    /// Whether the source started with a UTF-8 byte order mark
+    bom: bool,
+    /// The line ending used by the source, either `"\n"` or `"\r\n"`
+    line_ending: &'static str,
+    /// Whether the last line of the source ended with a line ending
+    final_line_ending: bool,
+}
+
+#[derive(Debug)]
+struct Line {
+    text: String,
+    statements: Vec<Statement>,
+}
+
+impl Document {
+    /// Parses every line of `source`. On failure, returns every error along with the zero-based
+    /// index of the offending line.
+    pub fn parse(source: &str) -> Result<Self, Vec<(ParseError, usize)>> {
+        let mut lines = Vec::new();
+        let mut errors = Vec::new();
+
+        for (index, text) in source_lines(source).enumerate() {
+            match parser::parse_line(text) {
+                Ok(statements) => lines.push(Line {
+                    text: text.to_owned(),
+                    statements,
+                }),
+                Err(e) => errors.push((e, index)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Document {
+                lines,
+                bom: source.starts_with(BOM),
+                line_ending: line_ending(source),
+                final_line_ending: source.is_empty() || source.ends_with('\n'),
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns every statement along with the zero-based index and text of its line.
+    pub fn statements(&self) -> impl Iterator<Item = (usize, &str, &Statement)> {
+        self.lines.iter().enumerate().flat_map(|(index, line)| {
+            line.statements
+                .iter()
+                .map(move |statement| (index, line.text.as_str(), statement))
+        })
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &ConfigItem> {
+        self.lines
+            .iter()
+            .flat_map(|line| &line.statements)
+            .map(|statement| &statement.item)
+    }
+
+    /// Returns the final item for every key, in the order the keys first appear. When a key is
+    /// set several times, the last item wins, as it does in game.
+    pub fn settings(&self) -> Vec<&ConfigItem> {
+        let mut settings: Vec<&ConfigItem> = Vec::new();
+        let mut indices = HashMap::new();
+
+        for item in self.items() {
+            match indices.get(&item.key()) {
+                Some(&index) => settings[index] = item,
+                None => {
+                    indices.insert(item.key(), settings.len());
+                    settings.push(item);
+                }
+            }
+        }
+
+        settings
+    }
+
+    /// Creates the patch turning this document into `new`, which sets every item that was added
+    /// or changed and removes every key that no longer exists. Removals come first, so they
+    /// cannot undo any of the items set after them.
+    pub fn diff(&self, new: &Document) -> String {
+        let old = self.settings();
+        let new = new.settings();
+        let mut patch = String::new();
+
+        for item in &old {
+            if !new.iter().any(|n| n.key() == item.key()) {
+                patch.push_str(&format!(
+                    "{} // {}\n",
+                    removal_directive(item),
+                    REMOVE_DIRECTIVE
+                ));
+            }
+        }
+
+        // Items which only differ in their quoting or the spelling of their key are unchanged
+        let unchanged: Vec<_> = old.iter().map(|item| item.normalized()).collect();
+        for item in &new {
+            if !unchanged.contains(&item.normalized()) {
+                patch.push_str(&format!("{}\n", item));
+            }
+        }
+
+        patch
+    }
+
+    /// Applies every item of `patch` onto this document. Items whose key already exists are
+    /// edited in place, leaving the rest of their line untouched, while new items are appended
+    /// on their own line at the end of the document. Items on a line annotated with
+    /// `// @remove` are instead removed from this document.
+    ///
+    /// Returns the keys of the items set by the patch.
+    pub fn patch(&mut self, patch: &Document) -> Vec<ConfigKey> {
+        let mut keys = Vec::new();
+
+        for line in &patch.lines {
+            let remove = line.is_removal();
+            for statement in &line.statements {
+                if remove {
+                    self.remove(&statement.item);
+                } else {
+                    self.set(&statement.item);
+                    keys.push(statement.item.key());
+                }
+            }
+        }
+
+        keys
+    }
+
+    /// Appends a line of text which holds no statements, such as a comment.
+    pub fn push_text(&mut self, text: String) {
+        self.lines.push(Line {
+            text,
+            statements: Vec::new(),
+        });
+    }
+
+    /// Sets every item with the same key as `item` to it, or appends `item` if there is none.
+    pub fn set(&mut self, item: &ConfigItem) {
+        let key = item.key();
+        let mut found = false;
+
+        for line in self.lines.iter_mut().filter(|l| l.has_key(&key)) {
+            line.replace(&key, item);
+            found = true;
+        }
+
+        if !found {
+            let text = item.to_string();
+            let span = 0..text.len();
+            self.lines.push(Line {
+                text,
+                statements: vec![Statement {
+                    item: item.clone(),
+                    span,
+                }],
+            });
+        }
+    }
+
+    /// Removes every statement matching the removal directive `item`.
+    fn remove(&mut self, item: &ConfigItem) {
+        self.remove_keys(&removal_keys(item));
+    }
+
+    /// Removes every statement with one of the given keys, along with any line left without
+    /// statements.
+    pub fn remove_keys(&mut self, keys: &[ConfigKey]) {
+        self.lines.retain(|line| {
+            let keep = |s: &Statement| !keys.contains(&s.item.key());
+            line.statements.is_empty() || line.statements.iter().any(keep)
+        });
+        for line in self.lines.iter_mut() {
+            line.retain(|s| !keys.contains(&s.item.key()));
+        }
+    }
+}
+
+/// The trailing comment marking the items of a patch line for removal.
+const REMOVE_DIRECTIVE: &str = "@remove";
+
+/// Returns the keys removed by a removal directive. Since a directive only needs to name the
+/// setting to remove, `bind "f"`, `alias "x"` and a bare `sensitivity` are read as the bind,
+/// alias or cvar they refer to.
+fn removal_keys(item: &ConfigItem) -> Vec<ConfigKey> {
+    match item {
+        ConfigItem::Command { name, args } => match (&name[..], &args[..]) {
+            ("bind", [key]) => vec![ConfigKey::bind(&key.value)],
+            ("alias", [name]) => vec![ConfigKey::Alias(name.value.clone())],
+            (name, []) => vec![item.key(), ConfigKey::cvar(name)],
+            _ => vec![item.key()],
+        },
+        _ => vec![item.key()],
+    }
+}
+
+/// Returns the shortest statement naming the setting of `item`, for use in a removal directive.
+fn removal_directive(item: &ConfigItem) -> String {
+    match item {
+        ConfigItem::Bind(key, _) => format!("bind {}", key),
+        ConfigItem::Alias(name, _) => format!("alias {}", name),
+        ConfigItem::Cvar(cvar, _) => cvar.clone(),
+        ConfigItem::Command { .. } => item.to_string(),
+    }
+}
+
+impl Line {
+    /// Returns whether the line ends with a `// @remove` comment.
+    fn is_removal(&self) -> bool {
+        let end = self.statements.last().map_or(0, |s| s.span.end);
+        let rest = &self.text[end..];
+
+        rest.find("//")
+            .is_some_and(|index| rest[index + 2..].trim() == REMOVE_DIRECTIVE)
+    }
+
+    fn has_key(&self, key: &ConfigKey) -> bool {
+        self.statements.iter().any(|s| s.item.key() == *key)
+    }
+
+    /// Replaces every statement with the given key by `item`, rebuilding the text of the line
+    /// around it. Statements which only differ from `item` in formatting, such as quoting or
+    /// the spelling of a key, keep their original text.
+    fn replace(&mut self, key: &ConfigKey, item: &ConfigItem) {
+        let mut text = String::with_capacity(self.text.len());
+        let mut end = 0;
+        let normalized = item.normalized();
+
+        for statement in &mut self.statements {
+            text.push_str(&self.text[end..statement.span.start]);
+            let start = text.len();
+
+            if statement.item.key() == *key && statement.item.normalized() != normalized {
+                text.push_str(&item.to_string());
+                statement.item = item.clone();
+            } else {
+                text.push_str(&self.text[statement.span.clone()]);
+            }
+
+            end = statement.span.end;
+            statement.span = start..text.len();
+        }
+        text.push_str(&self.text[end..]);
+
+        self.text = text;
+    }
+
+    /// Keeps only the statements matching `keep`, joining those that remain with `; ` in place
+    /// of the original statements.
+    fn retain(&mut self, keep: impl Fn(&Statement) -> bool) {
+        if self.statements.iter().all(&keep) {
+            return;
+        }
+
+        let start = self.statements.first().map_or(0, |s| s.span.start);
+        let end = self.statements.last().map_or(0, |s| s.span.end);
+        let mut text = self.text[..start].to_owned();
+        let mut statements = Vec::new();
+
+        for mut statement in self.statements.drain(..).filter(|s| keep(s)) {
+            if !statements.is_empty() {
+                text.push_str("; ");
+            }
+
+            let start = text.len();
+            text.push_str(&self.text[statement.span.clone()]);
+            statement.span = start..text.len();
+            statements.push(statement);
+        }
+        text.push_str(&self.text[end..]);
+
+        self.text = text;
+        self.statements = statements;
+    }
+}
+
+const BOM: char = '\u{feff}';
+
+/// Returns the lines of `source` without their line endings or any byte order mark.
+pub fn source_lines(source: &str) -> std::str::Lines<'_> {
+    source.trim_start_matches(BOM).lines()
+}
+
+/// Detects the line ending of `source` from its first line.
+fn line_ending(source: &str) -> &'static str {
+    match source.find('\n') {
+        Some(index) if source[..index].ends_with('\r') => "\r\n",
+        _ => "\n",
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.bom {
+            write!(f, "{}", BOM)?;
+        }
+
+        for (index, line) in self.lines.iter().enumerate() {
+            write!(f, "{}", line.text)?;
+            if index + 1 < self.lines.len() || self.final_line_ending {
+                write!(f, "{}", self.line_ending)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParseErrorKind;
+
+    fn patched(target: &str, patch: &str) -> Result<String, Vec<(ParseError, usize)>> {
+        let mut document = Document::parse(target)?;
+        document.patch(&Document::parse(patch)?);
+        Ok(document.to_string())
+    }
+
+    #[test]
+    fn test_patch_overrides_existing_items() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            patched(
+                "sensitivity 1.5\n  bind \"mouse1\" \"+attack\" // Shoot\n",
+                "sensitivity \"2\"\nbind \"mouse1\" \"+attack2\"\n"
+            )?,
+            "sensitivity \"2\"\n  bind \"mouse1\" \"+attack2\" // Shoot\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_matches_binds_by_key_name() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            patched(
+                "bind MOUSE1 +attack\nbind kp_ins \"buy ak47\"\nbind ESC cancelselect\n",
+                "bind mouse1 +attack2\nbind KP_0 \"buy m4a1\"\nbind escape \"\" // @remove\n"
+            )?,
+            "bind mouse1 +attack2\nbind KP_0 \"buy m4a1\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_matches_cvars_regardless_of_case() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            patched(
+                "Sensitivity 1.5\nVOLUME 0.5\nfps_max 300\n",
+                "sensitivity 2\nvolume // @remove\nFPS_MAX 0\n"
+            )?,
+            "sensitivity 2\nFPS_MAX 0\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_appends_new_items() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            patched(
+                "sensitivity \"1.5\"\n",
+                "volume \"0.5\"\nbind \"4\" \"slot4\"\n"
+            )?,
+            "sensitivity \"1.5\"\nvolume \"0.5\"\nbind \"4\" \"slot4\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_keeps_untouched_lines() -> Result<(), Vec<(ParseError, usize)>> {
+        let target =
+            "// Movement\nunbindall\n\nbind \"w\"    \"+forward\"\nvolume \"0.5\" // Quiet\n";
+        assert_eq!(
+            patched(target, "bind \"s\" \"+back\"\nvolume \"0.5\"\n")?,
+            format!("{}bind \"s\" \"+back\"\n", target)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_edits_multi_statement_lines() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            patched(
+                "unbindall; bind \"w\" \"+forward\"; cl_radar_scale 0.4 // Radar\n",
+                "cl_radar_scale \"0.35\"\nbind w +back\n"
+            )?,
+            "unbindall; bind w +back; cl_radar_scale \"0.35\" // Radar\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_overrides_aliases_by_name() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            patched(
+                "alias \"jumpthrow\" \"+jump;-attack\"\nbind \"v\" \"+jumpthrow\"\n",
+                "alias \"jumpthrow\" \"+jump; -attack; -attack2\"\n"
+            )?,
+            "alias \"jumpthrow\" \"+jump; -attack; -attack2\"\nbind \"v\" \"+jumpthrow\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_adds_commands_with_different_arguments() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            patched(
+                "exec binds\nincrementvar cl_radar_scale 0.25 1.0 0.05\n",
+                "exec \"binds\"\nexec nades\n"
+            )?,
+            "exec binds\nincrementvar cl_radar_scale 0.25 1.0 0.05\nexec nades\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_collects_all_errors() {
+        let errors = Document::parse("1quit\nbind \"w\" \"+forward\"\nvolume \"0.5\nunbindall\n")
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    ParseError {
+                        kind: ParseErrorKind::InvalidIdentifier("1quit".to_owned()),
+                        span: 0..5
+                    },
+                    0
+                ),
+                (
+                    ParseError {
+                        kind: ParseErrorKind::InvalidStringLiteral("0.5".to_owned()),
+                        span: 7..11
+                    },
+                    2
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_patch_keeps_line_endings_and_bom() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            patched(
+                "\u{feff}\tvolume \"0.5\"\r\nunbindall\r\n",
+                "volume 1\nbind w +forward\n"
+            )?,
+            "\u{feff}\tvolume 1\r\nunbindall\r\nbind w +forward\r\n"
+        );
+        assert_eq!(patched("volume \"0.5\"\n", "volume 1\r\n")?, "volume 1\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_keeps_formatting_of_unchanged_items() -> Result<(), Vec<(ParseError, usize)>> {
+        let target = "volume \"0.5\"; Sensitivity 2\nbind MOUSE1 \"+attack\"\n";
+        assert_eq!(
+            patched(
+                target,
+                "volume 0.5\nsensitivity \"2\"\nbind \"mouse1\" +attack\n"
+            )?,
+            target
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_keeps_missing_final_line_ending() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(patched("volume 0.5", "")?, "volume 0.5");
+        assert_eq!(patched("volume 0.5", "volume 0.5\n")?, "volume 0.5");
+        assert_eq!(
+            patched("volume 0.5\r\nunbindall", "bind w +forward\n")?,
+            "volume 0.5\r\nunbindall\r\nbind w +forward"
+        );
+        assert_eq!(patched("", "volume 1")?, "volume 1\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_removes_items() -> Result<(), Vec<(ParseError, usize)>> {
+        let target = "\
+unbindall
+bind \"f\" \"+lookatweapon\" // Inspect
+alias \"jt\" \"+jump; -attack\"
+sensitivity 2; volume 0.3 // Settings
+exec practice
+";
+        let patch = "\
+bind \"f\" // @remove
+alias jt //@remove
+sensitivity // @remove
+exec practice // @remove
+bind \"g\" // Not removed, since this is no directive
+";
+        assert_eq!(
+            patched(target, patch)?,
+            "unbindall\nvolume 0.3 // Settings\nbind \"g\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patches_are_layered() -> Result<(), Vec<(ParseError, usize)>> {
+        let mut document = Document::parse("sensitivity 1.5\nvolume 0.5\n")?;
+        let base = document.patch(&Document::parse("sensitivity 2\nbind mouse1 +attack\n")?);
+        let awp = document.patch(&Document::parse("sensitivity 1.8\n")?);
+
+        assert_eq!(
+            document.to_string(),
+            "sensitivity 1.8\nvolume 0.5\nbind mouse1 +attack\n"
+        );
+        assert_eq!(
+            base,
+            vec![
+                ConfigKey::Cvar("sensitivity".to_owned()),
+                ConfigKey::Bind("mouse1".to_owned())
+            ]
+        );
+        assert_eq!(awp, vec![ConfigKey::Cvar("sensitivity".to_owned())]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff() -> Result<(), Vec<(ParseError, usize)>> {
+        let old = "\
+unbindall
+bind \"f\" \"+lookatweapon\"
+alias \"jt\" \"+jump; -attack\"
+sensitivity 2
+sensitivity 1.5 // Overrides the line above
+volume 0.3
+exec practice
+";
+        let new = "\
+unbindall
+sensitivity 1.5
+volume 0.5 // Louder
+alias \"jt\" \"+jump; -attack; -attack2\"
+bind mouse4 +voicerecord
+";
+        let patch = Document::parse(old)?.diff(&Document::parse(new)?);
+        assert_eq!(
+            patch,
+            "\
+bind \"f\" // @remove
+exec practice // @remove
+volume 0.5
+alias \"jt\" \"+jump; -attack; -attack2\"
+bind mouse4 +voicerecord
+"
+        );
+
+        // Applying the patch gives the same settings, although not necessarily in the same order
+        let mut document = Document::parse(old)?;
+        document.patch(&Document::parse(&patch)?);
+        let new = Document::parse(new)?;
+        let mut patched = document.settings();
+        let mut expected = new.settings();
+        patched.sort();
+        expected.sort();
+        assert_eq!(patched, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_ignores_formatting() -> Result<(), Vec<(ParseError, usize)>> {
+        let old = "\
+sensitivity \"1.5\"
+bind \"MOUSE1\" \"+attack\"
+bind \"kp_ins\" \"buy ak47\"
+alias \"jt\" \"+jump; -attack\"
+";
+        let new = "\
+sensitivity 1.5
+bind mouse1 +attack
+bind KP_0 \"buy m4a1\"
+alias jt \"+jump; -attack\"
+";
+        assert_eq!(
+            Document::parse(old)?.diff(&Document::parse(new)?),
+            "bind KP_0 \"buy m4a1\"\n"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use crate::{
+    config::ConfigItem,
+    diagnostic::{Diagnostic, Severity},
+    document::Document,
+};
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// A config reachable from the target.
+pub struct Config {
+    pub path: PathBuf,
+    pub document: Document,
+}
+
+/// The configs reachable from a target through `exec`, along with the problems found executing
+/// them.
+pub struct Graph {
+    /// Every reachable config, starting with the target, in the order they are first executed
+    pub configs: Vec<Config>,
+    /// Missing configs and cycles, along with the index of the config whose `exec` they were
+    /// found at
+    pub diagnostics: Vec<(usize, Diagnostic)>,
+}
+
+/// Resolves the target of `exec` within `root`, adding `.cfg` unless it is there already like
+/// the game does. Going up from `root` stays at `root`.
+pub fn resolve(root: &Path, name: &str) -> PathBuf {
+    let mut segments: Vec<_> = name
+        .split(['/', '\\'])
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_owned)
+        .collect();
+    if let Some(last) = segments.last_mut() {
+        if !last.to_ascii_lowercase().ends_with(".cfg") {
+            last.push_str(".cfg");
+        }
+    }
+
+    // Each segment is pushed on its own, since `/` is no separator within the verbatim paths
+    // `canonicalize` returns on Windows. Removing `.` and `..` along the way means the same
+    // config is always reached by the same path, and `..` never leaves the root.
+    let mut path = root.to_owned();
+    let mut depth = 0;
+    for segment in &segments {
+        match segment.as_str() {
+            "." => {}
+            ".." if depth == 0 => {}
+            ".." => {
+                path.pop();
+                depth -= 1;
+            }
+            segment => {
+                path.push(segment);
+                depth += 1;
+            }
+        }
+    }
+
+    path
+}
+
+/// Follows every `exec` from `target` onwards, loading the configs it reaches with `load`,
+/// which returns `None` for a config which does not exist. Each config is loaded once, even if
+/// it is executed from several others.
+pub fn graph<E>(
+    root: &Path,
+    target: PathBuf,
+    document: Document,
+    load: impl FnMut(&Path) -> Result<Option<Document>, E>,
+) -> Result<Graph, E> {
+    let mut walk = Walk {
+        root,
+        load,
+        chain: Vec::new(),
+        graph: Graph {
+            configs: vec![Config {
+                path: target,
+                document,
+            }],
+            diagnostics: Vec::new(),
+        },
+    };
+    walk.visit(0)?;
+
+    Ok(walk.graph)
+}
+
+struct Walk<'a, F> {
+    root: &'a Path,
+    load: F,
+    /// The indices of the configs being executed, from the target to the current one
+    chain: Vec<usize>,
+    graph: Graph,
+}
+
+impl<E, F: FnMut(&Path) -> Result<Option<Document>, E>> Walk<'_, F> {
+    fn visit(&mut self, index: usize) -> Result<(), E> {
+        self.chain.push(index);
+
+        for (line_index, line, span, name) in execs(&self.graph.configs[index].document) {
+            let path = resolve(self.root, &name);
+            let configs = &self.graph.configs;
+            let problem = if self.chain.iter().any(|i| configs[*i].path == path) {
+                let message = format!(
+                    "executing `{}` again forms a cycle ({})",
+                    self.name(&path),
+                    self.chain_to(&path)
+                );
+                Some(("exec-cycle", message))
+            } else if configs.iter().any(|config| config.path == path) {
+                None
+            } else {
+                match (self.load)(&path)? {
+                    Some(document) => {
+                        self.graph.configs.push(Config {
+                            path: path.clone(),
+                            document,
+                        });
+                        self.visit(self.graph.configs.len() - 1)?;
+                        None
+                    }
+                    None => {
+                        let message = format!(
+                            "cannot find `{}` to execute ({})",
+                            self.name(&path),
+                            self.chain_to(&path)
+                        );
+                        Some(("missing-exec", message))
+                    }
+                }
+            };
+
+            if let Some((rule, message)) = problem {
+                let diagnostic = Diagnostic::new(Severity::Error, message, line_index, &line, span);
+                self.graph
+                    .diagnostics
+                    .push((index, diagnostic.with_rule(rule)));
+            }
+        }
+
+        self.chain.pop();
+        Ok(())
+    }
+
+    /// Returns the path of a config relative to the root, as the game would name it.
+    fn name<'p>(&self, path: &'p Path) -> std::path::Display<'p> {
+        path.strip_prefix(self.root).unwrap_or(path).display()
+    }
+
+    /// Describes the chain of configs which led to executing `path`, like
+    /// `autoexec.cfg -> practice.cfg -> nades.cfg`.
+    fn chain_to(&self, path: &Path) -> String {
+        let names: Vec<_> = self
+            .chain
+            .iter()
+            .map(|i| self.graph.configs[*i].path.as_path())
+            .chain(Some(path))
+            .map(|path| self.name(path).to_string())
+            .collect();
+        names.join(" -> ")
+    }
+}
+
+/// Returns the line index, line, argument span and target of every `exec` in a config. Those
+/// within aliases are left out, since they only run if the alias is.
+fn execs(document: &Document) -> Vec<(usize, String, Range<usize>, String)> {
+    document
+        .statements()
+        .filter_map(|(index, line, statement)| match &statement.item {
+            ConfigItem::Command { name, args } if name.eq_ignore_ascii_case("exec") => {
+                let target = args.first()?;
+                let span = statement.argument_spans(line)[0].clone();
+                Some((index, line.to_owned(), span, target.value.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParseError;
+    use std::collections::HashMap;
+
+    /// The name of the config, line number, rule and message of a diagnostic.
+    type Found = (String, usize, &'static str, String);
+    /// The names of the configs reached, along with what was found.
+    type Walked = (Vec<String>, Vec<Found>);
+
+    fn walked(files: &[(&str, &str)]) -> Result<Walked, Vec<(ParseError, usize)>> {
+        let root = Path::new("/cfg");
+        let files: HashMap<_, _> = files
+            .iter()
+            .map(|(name, source)| (root.join(name), *source))
+            .collect();
+        let target = root.join("autoexec.cfg");
+        let document = Document::parse(files[&target])?;
+
+        let graph = graph(root, target, document, |path| {
+            files
+                .get(path)
+                .map(|source| Document::parse(source))
+                .transpose()
+        })?;
+
+        let name = |index: usize| {
+            let path = &graph.configs[index].path;
+            path.strip_prefix(root).unwrap().display().to_string()
+        };
+        let configs = (0..graph.configs.len()).map(name).collect();
+        let diagnostics = graph
+            .diagnostics
+            .iter()
+            .map(|(i, d)| (name(*i), d.line_number, d.rule.unwrap(), d.message.clone()))
+            .collect();
+
+        Ok((configs, diagnostics))
+    }
+
+    #[test]
+    fn test_resolve() {
+        let root = Path::new("/cfg");
+
+        assert_eq!(resolve(root, "practice"), Path::new("/cfg/practice.cfg"));
+        assert_eq!(resolve(root, "Binds.CFG"), Path::new("/cfg/Binds.CFG"));
+        assert_eq!(
+            resolve(root, "binds\\nades"),
+            Path::new("/cfg/binds/nades.cfg")
+        );
+        assert_eq!(
+            resolve(root, "./binds/../practice"),
+            Path::new("/cfg/practice.cfg")
+        );
+        assert_eq!(
+            resolve(root, "../../etc/hostname"),
+            Path::new("/cfg/etc/hostname.cfg")
+        );
+
+        let nested = resolve(root, "binds\\grenades//nades");
+        let segments: Vec<_> = nested.iter().map(|s| s.to_str().unwrap()).collect();
+        assert_eq!(segments, vec!["/", "cfg", "binds", "grenades", "nades.cfg"]);
+    }
+
+    #[test]
+    fn test_graph() -> Result<(), Vec<(ParseError, usize)>> {
+        let (configs, diagnostics) = walked(&[
+            (
+                "autoexec.cfg",
+                "exec practice; exec binds/nades\nexec \"practice.cfg\"\n",
+            ),
+            ("practice.cfg", "sv_cheats 1\nexec binds/nades\n"),
+            ("binds/nades.cfg", "alias nades \"exec missing\"\n"),
+        ])?;
+
+        assert_eq!(
+            configs,
+            vec!["autoexec.cfg", "practice.cfg", "binds/nades.cfg"]
+        );
+        assert_eq!(diagnostics, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_configs_and_cycles() -> Result<(), Vec<(ParseError, usize)>> {
+        let (configs, diagnostics) = walked(&[
+            ("autoexec.cfg", "exec practice\nexec practice.cfg\n"),
+            ("practice.cfg", "exec binds\nexec autoexec\n"),
+        ])?;
+
+        assert_eq!(configs, vec!["autoexec.cfg", "practice.cfg"]);
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "practice.cfg".to_owned(),
+                    1,
+                    "missing-exec",
+                    "cannot find `binds.cfg` to execute \
+                     (autoexec.cfg -> practice.cfg -> binds.cfg)"
+                        .to_owned()
+                ),
+                (
+                    "practice.cfg".to_owned(),
+                    2,
+                    "exec-cycle",
+                    "executing `autoexec.cfg` again forms a cycle \
+                     (autoexec.cfg -> practice.cfg -> autoexec.cfg)"
+                        .to_owned()
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use std::{fmt::Write, path::Path};
+
+/// How diagnostics are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    /// Rendered with a snippet of the offending line, on stderr
+    #[default]
+    Human,
+    /// A JSON array with an object per diagnostic, on stdout
+    Json,
+    /// A SARIF 2.1.0 log, as read by code scanning tools, on stdout
+    Sarif,
+}
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Formats diagnostics, along with the path of the file each was found in, as a JSON array.
+pub fn json<'a>(diagnostics: impl IntoIterator<Item = (&'a Path, &'a Diagnostic)>) -> String {
+    let objects: Vec<_> = diagnostics
+        .into_iter()
+        .map(|(path, diagnostic)| {
+            format!(
+                "  {{\"file\": {}, \"line\": {}, \"column\": {}, \"end_line\": {}, \
+                 \"end_column\": {}, \"severity\": {}, \"rule\": {}, \"message\": {}}}",
+                string(&path.display().to_string()),
+                diagnostic.line_number,
+                diagnostic.column(),
+                diagnostic.line_number,
+                diagnostic.end_column(),
+                string(severity(diagnostic.severity)),
+                diagnostic.rule.map_or_else(|| "null".to_owned(), string),
+                string(&diagnostic.message),
+            )
+        })
+        .collect();
+
+    if objects.is_empty() {
+        "[]".to_owned()
+    } else {
+        format!("[\n{}\n]", objects.join(",\n"))
+    }
+}
+
+/// Formats diagnostics, along with the path of the file each was found in, as a SARIF log with
+/// a single run.
+pub fn sarif<'a>(diagnostics: impl IntoIterator<Item = (&'a Path, &'a Diagnostic)>) -> String {
+    let results: Vec<_> = diagnostics
+        .into_iter()
+        .map(|(path, diagnostic)| {
+            format!(
+                "        {{\n\
+                 \x20         \"ruleId\": {rule},\n\
+                 \x20         \"level\": {level},\n\
+                 \x20         \"message\": {{\"text\": {message}}},\n\
+                 \x20         \"locations\": [{{\"physicalLocation\": {{\
+                 \"artifactLocation\": {{\"uri\": {uri}}}, \
+                 \"region\": {{\"startLine\": {line}, \"startColumn\": {column}, \
+                 \"endColumn\": {end_column}}}}}}}]\n\
+                 \x20       }}",
+                rule = diagnostic.rule.map_or_else(|| "null".to_owned(), string),
+                level = string(severity(diagnostic.severity)),
+                message = string(&diagnostic.message),
+                uri = string(&uri(path)),
+                line = diagnostic.line_number,
+                column = diagnostic.column(),
+                end_column = diagnostic.end_column(),
+            )
+        })
+        .collect();
+    let results = if results.is_empty() {
+        "[]".to_owned()
+    } else {
+        format!("[\n{}\n      ]", results.join(",\n"))
+    };
+
+    format!(
+        "{{\n  \"$schema\": {schema},\n  \"version\": \"2.1.0\",\n  \"runs\": [\n    {{\n      \
+         \"tool\": {{\"driver\": {{\"name\": \"csgocfg\", \"version\": {version}}}}},\n      \
+         \"columnKind\": \"unicodeCodePoints\",\n      \"results\": {results}\n    }}\n  ]\n}}",
+        schema = string(SARIF_SCHEMA),
+        version = string(env!("CARGO_PKG_VERSION")),
+        results = results,
+    )
+}
+
+/// The name of a severity, which is also its SARIF level.
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+/// Quotes and escapes a string as a JSON string literal.
+fn string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Turns a path into a URI reference, which is a `file` URI for absolute paths.
+fn uri(path: &Path) -> String {
+    // Canonical paths on Windows are verbatim, like `\\?\C:\cfg` or `\\?\UNC\server\cfg`
+    let path = path.to_string_lossy();
+    let path = match path.strip_prefix(r"\\?\UNC\") {
+        Some(path) => format!(r"\\{}", path),
+        None => path.strip_prefix(r"\\?\").unwrap_or(&path).to_owned(),
+    };
+
+    let path = path.replace('\\', "/");
+    let mut uri = String::new();
+    if path.starts_with("//") {
+        // A UNC path, whose server is the authority of the URI
+        uri.push_str("file:");
+    } else if path.starts_with('/') {
+        uri.push_str("file://");
+    } else if path.as_bytes().get(1) == Some(&b':') {
+        // A Windows path starting with a drive letter
+        uri.push_str("file:///");
+    }
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            byte => write!(uri, "%{:02X}", byte).unwrap(),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics() -> Vec<Diagnostic> {
+        vec![
+            Diagnostic::new(
+                Severity::Error,
+                "unknown key `mouse_4`, did you mean `mouse4`?".to_owned(),
+                0,
+                "bind \"mouse_4\" +voicerecord",
+                5..14,
+            )
+            .with_rule("unknown-key"),
+            Diagnostic::new(
+                Severity::Warning,
+                "`f` is already bound on line 1".to_owned(),
+                2,
+                "bind f +use",
+                5..6,
+            )
+            .with_rule("duplicate-bind"),
+        ]
+    }
+
+    #[test]
+    fn test_json() {
+        let diagnostics = diagnostics();
+        let path = Path::new("my binds.cfg");
+
+        assert_eq!(json(Vec::new()), "[]");
+        assert_eq!(
+            json(diagnostics.iter().map(|d| (path, d))),
+            r#"[
+  {"file": "my binds.cfg", "line": 1, "column": 6, "end_line": 1, "end_column": 15, "severity": "error", "rule": "unknown-key", "message": "unknown key `mouse_4`, did you mean `mouse4`?"},
+  {"file": "my binds.cfg", "line": 3, "column": 6, "end_line": 3, "end_column": 7, "severity": "warning", "rule": "duplicate-bind", "message": "`f` is already bound on line 1"}
+]"#
+        );
+    }
+
+    #[test]
+    fn test_sarif() {
+        let diagnostics = diagnostics();
+        let path = Path::new("/cfg/my binds.cfg");
+        let sarif = sarif(diagnostics.iter().map(|d| (path, d)).take(1));
+
+        assert!(sarif.contains("\"version\": \"2.1.0\""));
+        assert!(sarif.contains(
+            r#"
+        {
+          "ruleId": "unknown-key",
+          "level": "error",
+          "message": {"text": "unknown key `mouse_4`, did you mean `mouse4`?"},
+          "locations": [{"physicalLocation": {"artifactLocation": {"uri": "file:///cfg/my%20binds.cfg"}, "region": {"startLine": 1, "startColumn": 6, "endColumn": 15}}}]
+        }
+      ]"#
+        ));
+    }
+
+    #[test]
+    fn test_uri() {
+        assert_eq!(
+            uri(Path::new("/cfg/my binds.cfg")),
+            "file:///cfg/my%20binds.cfg"
+        );
+        assert_eq!(uri(Path::new("cfg/<stdin>")), "cfg/%3Cstdin%3E");
+        assert_eq!(
+            uri(Path::new(r"C:\Steam\cfg\autoexec.cfg")),
+            "file:///C:/Steam/cfg/autoexec.cfg"
+        );
+        assert_eq!(
+            uri(Path::new(r"\\?\C:\Steam\cfg\autoexec.cfg")),
+            "file:///C:/Steam/cfg/autoexec.cfg"
+        );
+        assert_eq!(
+            uri(Path::new(r"\\?\UNC\server\cfg\autoexec.cfg")),
+            "file://server/cfg/autoexec.cfg"
+        );
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(string("bind \"f\" \\\t\u{1}"), r#""bind \"f\" \\\t\u0001""#);
+    }
+}
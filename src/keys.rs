@@ -0,0 +1,295 @@
+/// The names of the keys and buttons the game accepts in `bind`, in lowercase.
+const KEYS: &[&str] = &[
+    // Letters and digits
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "0",
+    "1",
+    "2",
+    "3",
+    "4",
+    "5",
+    "6",
+    "7",
+    "8",
+    "9",
+    // Punctuation, where `;` has to be named since it separates statements
+    "`",
+    "-",
+    "=",
+    "[",
+    "]",
+    "\\",
+    "semicolon",
+    "'",
+    ",",
+    ".",
+    "/",
+    // Function keys
+    "f1",
+    "f2",
+    "f3",
+    "f4",
+    "f5",
+    "f6",
+    "f7",
+    "f8",
+    "f9",
+    "f10",
+    "f11",
+    "f12",
+    // Special keys
+    "space",
+    "tab",
+    "enter",
+    "escape",
+    "backspace",
+    "capslock",
+    "scrolllock",
+    "numlock",
+    "pause",
+    "shift",
+    "rshift",
+    "ctrl",
+    "rctrl",
+    "alt",
+    "ralt",
+    "lwin",
+    "rwin",
+    "app",
+    "ins",
+    "del",
+    "home",
+    "end",
+    "pgup",
+    "pgdn",
+    "uparrow",
+    "downarrow",
+    "leftarrow",
+    "rightarrow",
+    // Keypad
+    "kp_ins",
+    "kp_end",
+    "kp_downarrow",
+    "kp_pgdn",
+    "kp_leftarrow",
+    "kp_5",
+    "kp_rightarrow",
+    "kp_home",
+    "kp_uparrow",
+    "kp_pgup",
+    "kp_del",
+    "kp_slash",
+    "kp_multiply",
+    "kp_minus",
+    "kp_plus",
+    "kp_enter",
+    // Mouse
+    "mouse1",
+    "mouse2",
+    "mouse3",
+    "mouse4",
+    "mouse5",
+    "mwheelup",
+    "mwheeldown",
+    // Joystick
+    "joy1",
+    "joy2",
+    "joy3",
+    "joy4",
+    "joy5",
+    "joy6",
+    "joy7",
+    "joy8",
+    "joy9",
+    "joy10",
+    "joy11",
+    "joy12",
+    "joy13",
+    "joy14",
+    "joy15",
+    "joy16",
+    "joy17",
+    "joy18",
+    "joy19",
+    "joy20",
+    "joy21",
+    "joy22",
+    "joy23",
+    "joy24",
+    "joy25",
+    "joy26",
+    "joy27",
+    "joy28",
+    "joy29",
+    "joy30",
+    "joy31",
+    "joy32",
+    "aux1",
+    "aux2",
+    "aux3",
+    "aux4",
+    "aux5",
+    "aux6",
+    "aux7",
+    "aux8",
+    "aux9",
+    "aux10",
+    "aux11",
+    "aux12",
+    "aux13",
+    "aux14",
+    "aux15",
+    "aux16",
+    "aux17",
+    "aux18",
+    "aux19",
+    "aux20",
+    "aux21",
+    "aux22",
+    "aux23",
+    "aux24",
+    "aux25",
+    "aux26",
+    "aux27",
+    "aux28",
+    "aux29",
+    "aux30",
+    "aux31",
+    "aux32",
+    "pov_up",
+    "pov_right",
+    "pov_down",
+    "pov_left",
+];
+
+/// Other names for keys, along with the name the game knows them by.
+const ALIASES: &[(&str, &str)] = &[
+    ("esc", "escape"),
+    ("return", "enter"),
+    ("spacebar", "space"),
+    ("lshift", "shift"),
+    ("lctrl", "ctrl"),
+    ("lalt", "alt"),
+    ("insert", "ins"),
+    ("delete", "del"),
+    ("pageup", "pgup"),
+    ("pagedown", "pgdn"),
+    ("up", "uparrow"),
+    ("down", "downarrow"),
+    ("left", "leftarrow"),
+    ("right", "rightarrow"),
+    ("kp_0", "kp_ins"),
+    ("kp_1", "kp_end"),
+    ("kp_2", "kp_downarrow"),
+    ("kp_3", "kp_pgdn"),
+    ("kp_4", "kp_leftarrow"),
+    ("kp_6", "kp_rightarrow"),
+    ("kp_7", "kp_home"),
+    ("kp_8", "kp_uparrow"),
+    ("kp_9", "kp_pgup"),
+    ("kp_dot", "kp_del"),
+];
+
+/// Returns the name the game knows a key by, ignoring case and resolving aliases, or `None` if
+/// there is no such key.
+pub fn canonical(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+
+    KEYS.iter()
+        .find(|key| **key == name)
+        .or_else(|| {
+            ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == name)
+                .map(|(_, key)| key)
+        })
+        .copied()
+}
+
+/// Suggests the key closest to an unknown key name, if any is close enough to be a likely typo.
+/// Keys the name is an abbreviation of are preferred.
+pub fn suggest(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    let score = |key: &str| {
+        if name.len() >= 3 && key.starts_with(&name) {
+            0
+        } else {
+            distance(&name, key)
+        }
+    };
+
+    KEYS.iter()
+        .chain(ALIASES.iter().map(|(alias, _)| alias))
+        .map(|key| (score(key), *key))
+        .filter(|(distance, key)| *distance <= 2 && *distance < key.len())
+        .min()
+        .map(|(_, key)| key)
+}
+
+/// The Levenshtein distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous + if a == *b { 0 } else { 1 };
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(canonical("MOUSE1"), Some("mouse1"));
+        assert_eq!(canonical("kp_0"), Some("kp_ins"));
+        assert_eq!(canonical("Esc"), Some("escape"));
+        assert_eq!(canonical("JOY32"), Some("joy32"));
+        assert_eq!(canonical("aux1"), Some("aux1"));
+        assert_eq!(canonical("POV_LEFT"), Some("pov_left"));
+        assert_eq!(canonical("mouse_4"), None);
+        assert_eq!(canonical("kp_ent"), None);
+    }
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(suggest("mouse_4"), Some("mouse4"));
+        assert_eq!(suggest("kp_ent"), Some("kp_enter"));
+        assert_eq!(suggest("MWHEELUPP"), Some("mwheelup"));
+        assert_eq!(suggest("grenade"), None);
+    }
+}
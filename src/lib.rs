@@ -1,12 +1,30 @@
+#[macro_use]
+mod output;
+
+mod backup;
+mod cli;
 mod config;
+mod cvars;
+mod diagnostic;
+mod diff;
+mod document;
+mod exec;
+mod format;
+mod keys;
+mod lint;
+mod merge;
 mod parser;
+mod validation;
 
-use config::ConfigItem;
-use parser::ParseError;
+use cli::Command;
+use diagnostic::{Diagnostic, Severity};
+use document::Document;
+use format::Format;
+use output::Style;
 use std::{
-    collections::BTreeSet,
-    fs::{File, OpenOptions},
-    io::{BufRead, BufReader, Write},
+    collections::HashMap,
+    fs,
+    io::{self, Read},
     path::{Path, PathBuf},
 };
 use thiserror::Error;
@@ -17,143 +35,634 @@ pub enum Error {
     NoCommandSpecified,
     #[error("unrecognized command `{0}`")]
     UnrecognizedCommand(String),
+    #[error("unrecognized option `{0}`")]
+    UnrecognizedOption(String),
+    #[error("invalid value `{1}` for option `{0}`")]
+    InvalidOptionValue(&'static str, String),
     #[error("missing argument `{0}`")]
     MissingArgument(&'static str),
+    #[error("unexpected argument `{0}`")]
+    UnexpectedArgument(String),
+    #[error("stdin can only be read once")]
+    StdinReadTwice,
+    #[error("`{0}` must be a file, not stdin")]
+    StdinNotSupported(&'static str),
     #[error("file not found `{0}`")]
     FileNotFound(String),
     #[error("error reading file, {0}")]
     FileReadError(#[from] std::io::Error),
-    #[error("parse error at line {line_number},\n{source}")]
-    ParseError {
-        source: ParseError,
-        line_number: usize,
+    #[error(
+        "could not parse `{}` due to {} error(s)",
+        .path.display(),
+        .diagnostics.len()
+    )]
+    InvalidConfig {
+        path: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+    #[error("unknown lint rule `{0}`")]
+    UnknownRule(String),
+    #[error(
+        "`{}` failed {} with {} error(s) and {} warning(s)",
+        .path.display(),
+        .check,
+        .errors,
+        .warnings
+    )]
+    CheckFailed {
+        path: PathBuf,
+        /// The name of the check, like `validation`
+        check: &'static str,
+        errors: usize,
+        warnings: usize,
     },
+    #[error("patching would modify `{}`", .0.display())]
+    PatchWouldModify(PathBuf),
+    #[error("{0} merge conflict(s)")]
+    MergeConflicts(usize),
+    #[error("no backups found for `{}`", .0.display())]
+    NoBackups(PathBuf),
+    #[error("no backup `{id}` found for `{}`", .target.display())]
+    BackupNotFound { target: PathBuf, id: String },
+}
+
+#[derive(Debug)]
+pub struct PatchOptions {
+    /// Print a diff of the changes instead of writing them
+    pub dry_run: bool,
+    /// Fail if the patch would modify the target, without writing it
+    pub check: bool,
+    /// The number of backups of the target to keep, where zero disables backups
+    pub backups: usize,
+    /// Print which patch set the final value of each item to stderr
+    pub explain: bool,
+    /// Where to write the result instead of the target, where `-` is stdout
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+pub struct ValidateOptions {
+    /// Fail on warnings as well as errors
+    pub deny_warnings: bool,
+    /// How to print the diagnostics
+    pub format: Format,
+    /// The directory `exec` targets are resolved in, instead of the directory of the target
+    pub root: Option<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+pub struct LintOptions {
+    /// The level of each rule given on the command line, overriding its default level
+    pub levels: HashMap<&'static str, lint::Level>,
+    /// Fail on warnings as well as errors
+    pub deny_warnings: bool,
+    /// How to print the diagnostics
+    pub format: Format,
 }
 
-/// Turns a tuple of a `ParseError` and a zero-based index into `Error::ParseError`
-impl From<(ParseError, usize)> for Error {
-    fn from((error, line): (ParseError, usize)) -> Self {
-        Error::ParseError {
-            source: error,
-            line_number: line + 1,
+impl Default for PatchOptions {
+    fn default() -> Self {
+        PatchOptions {
+            dry_run: false,
+            check: false,
+            backups: 5,
+            explain: false,
+            output: None,
         }
     }
 }
 
-enum Command {
-    Patch { target: PathBuf, patch: PathBuf },
-    Validate { target: PathBuf },
-    Unrecognized(String),
+/// The path which stands for stdin when reading a config, or stdout when writing one.
+const STANDARD_STREAM: &str = "-";
+
+/// The codes the process exits with. They are part of the interface of the tool, so that scripts
+/// and hooks can tell failures apart, and must not change.
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// The inputs could be parsed, but a check failed, such as validation finding problems,
+    /// `patch --check` finding changes or a merge having conflicts
+    pub const FAILURE: i32 = 1;
+    /// The tool was invoked incorrectly
+    pub const USAGE: i32 = 2;
+    /// A file could not be found, read or written
+    pub const IO: i32 = 3;
+    /// A config could not be parsed
+    pub const INVALID_CONFIG: i32 = 4;
+}
+
+impl Error {
+    /// Whether the error is in how the tool was invoked, rather than in what it was asked to do.
+    pub fn is_usage(&self) -> bool {
+        self.exit_code() == exit_code::USAGE
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::NoCommandSpecified
+            | Error::UnrecognizedCommand(_)
+            | Error::UnrecognizedOption(_)
+            | Error::InvalidOptionValue(..)
+            | Error::MissingArgument(_)
+            | Error::UnexpectedArgument(_)
+            | Error::StdinReadTwice
+            | Error::StdinNotSupported(_)
+            | Error::UnknownRule(_) => exit_code::USAGE,
+            Error::FileNotFound(_)
+            | Error::FileReadError(_)
+            | Error::NoBackups(_)
+            | Error::BackupNotFound { .. } => exit_code::IO,
+            Error::InvalidConfig { .. } => exit_code::INVALID_CONFIG,
+            Error::CheckFailed { .. } | Error::PatchWouldModify(_) | Error::MergeConflicts(_) => {
+                exit_code::FAILURE
+            }
+        }
+    }
 }
 
 pub fn run() -> Result<(), Error> {
-    let command = parse_args(std::env::args().skip(1))?;
+    let cli = cli::parse(std::env::args().skip(1))?;
+    output::init(cli.verbosity, cli.color);
 
-    match command {
-        Command::Patch { target, patch } => apply_patch(&target, &patch)?,
-        Command::Validate { target } => validate(&target)?,
-        Command::Unrecognized(s) => return Err(Error::UnrecognizedCommand(s)),
+    match cli.command {
+        Command::Patch {
+            target,
+            patches,
+            options,
+        } => apply_patches(&target, &patches, &options)?,
+        Command::Validate { target, options } => validate(&target, &options)?,
+        Command::Lint { target, options } => lint(&target, &options)?,
+        Command::LintRules => list_lint_rules(),
+        Command::Restore {
+            target, list: true, ..
+        } => list_backups(&target)?,
+        Command::Restore { target, to, .. } => restore(&target, to.as_deref())?,
+        Command::Diff { old, new } => diff(&old, &new)?,
+        Command::Merge { base, ours, theirs } => merge(&base, &ours, &theirs)?,
+        Command::MergeDriver { base, ours, theirs } => merge_driver(&base, &ours, &theirs)?,
+        Command::Help(subcommand) => println!("{}", cli::help(subcommand)),
+        Command::Version => println!("csgocfg {}", env!("CARGO_PKG_VERSION")),
     }
 
     Ok(())
 }
 
-pub fn usage() {
-    println!(
-        "usage: csgocfg <command> [<args>]
+/// Prints `error` to stderr, preceded by its diagnostics if it has any, or followed by a hint
+/// about the usage of the tool if it was invoked incorrectly.
+pub fn report(error: &Error) {
+    match error {
+        Error::NoCommandSpecified => eprintln!("{}", cli::help(None)),
+        Error::InvalidConfig { path, diagnostics } => {
+            print_diagnostics(
+                diagnostics.iter().map(|d| (path.as_path(), d)),
+                Format::Human,
+            );
+            eprintln!("{} {}", output::paint("error:", Style::Error), error);
+        }
+        _ if error.is_usage() => eprintln!(
+            "{} {}
 
-Valid commands are:
+Run `csgocfg --help` for usage.",
+            output::paint("error:", Style::Error),
+            error
+        ),
+        _ => eprintln!("{} {}", output::paint("error:", Style::Error), error),
+    }
+}
 
-    patch <target> <patch>      Applies the given patch onto the target
-    validate <file>             Validates a config file"
-    );
+/// Applies `patches` onto `target` one after the other, so that later patches take precedence,
+/// and writes the result once all of them have been applied. The result is written back to the
+/// target unless another output is given, or the target was read from stdin, in which case it
+/// is written to stdout.
+pub fn apply_patches(
+    target: &Path,
+    patches: &[PathBuf],
+    options: &PatchOptions,
+) -> Result<(), Error> {
+    let source = read_source(target)?;
+    let mut document = parse_config(target, &source)?;
+
+    let mut origins = HashMap::new();
+    for patch in patches {
+        let keys = document.patch(&read_config(patch)?);
+        verbose!(
+            "Applied `{}`, setting {} item(s).",
+            display_name(patch).display(),
+            keys.len()
+        );
+        for key in keys {
+            origins.insert(key, patch);
+        }
+    }
+    let patched = document.to_string();
+
+    // Explanations go to stderr, so that they never end up in a config written to stdout
+    if options.explain {
+        for item in document.settings() {
+            let origin = origins
+                .get(&item.key())
+                .map_or(target, |patch| patch.as_path());
+            eprintln!("{}  ({})", item, display_name(origin).display());
+        }
+    }
+
+    if options.dry_run {
+        let name = display_name(target).display().to_string();
+        let diff = diff::unified_diff(&source, &patched, &name, &name);
+        print!("{}", output::paint_diff(&diff));
+    }
+
+    if options.check && patched != source {
+        return Err(Error::PatchWouldModify(display_name(target).to_owned()));
+    }
+
+    if options.dry_run || options.check {
+        return Ok(());
+    }
+
+    let output = match &options.output {
+        Some(output) => output.as_path(),
+        None if is_standard_stream(target) => Path::new(STANDARD_STREAM),
+        None => target,
+    };
+    if is_standard_stream(output) {
+        print!("{}", patched);
+        return Ok(());
+    }
+
+    if output == target && patched == source {
+        info!("`{}` is already up to date.", target.display());
+        return Ok(());
+    }
+
+    if options.backups > 0 && output.exists() {
+        let backup = backup::create(output, options.backups)?;
+        info!(
+            "Backed up `{}` to `{}`.",
+            output.display(),
+            backup.path.display()
+        );
+    }
+    backup::write_atomic(output, &patched)?;
+
+    let patches: Vec<_> = patches
+        .iter()
+        .map(|patch| format!("`{}`", display_name(patch).display()))
+        .collect();
+    if output == target {
+        info!(
+            "Successfully patched {} onto `{}`.",
+            patches.join(", "),
+            target.display()
+        );
+    } else {
+        info!(
+            "Successfully patched {} onto `{}`, writing the result to `{}`.",
+            patches.join(", "),
+            display_name(target).display(),
+            output.display()
+        );
+    }
+
+    Ok(())
 }
 
-pub fn apply_patch(target: &Path, patch: &Path) -> Result<(), Error> {
-    let mut config_set: BTreeSet<ConfigItem> = BTreeSet::new();
+/// Checks that `target` parses and that the values it sets are valid, printing any problems.
+/// The configs it executes are checked as well, along with those they execute in turn.
+pub fn validate(target: &Path, options: &ValidateOptions) -> Result<(), Error> {
+    let document = read_checked_config(target, options.format)?;
+    let path = display_name(target);
 
-    let target_reader = BufReader::new(File::open(target)?);
-    for (index, line) in target_reader.lines().enumerate() {
-        let line = line?;
-        let config_item = parser::parse_line(&line).map_err(|e| (e, index))?;
+    let root = match &options.root {
+        Some(root) => root.clone(),
+        None if is_standard_stream(target) => std::env::current_dir()?,
+        None => target.parent().unwrap_or_else(|| Path::new("")).to_owned(),
+    };
+    let graph = exec::graph(&root, path.to_owned(), document, |config| {
+        if config.is_file() {
+            read_checked_config(config, options.format).map(Some)
+        } else {
+            Ok(None)
+        }
+    })?;
 
-        if let Some(item) = config_item {
-            config_set.insert(item);
+    let mut found: Vec<_> = graph
+        .configs
+        .iter()
+        .map(|config| validation::validate(&config.document))
+        .collect();
+    for (index, diagnostic) in graph.diagnostics {
+        found[index].push(diagnostic);
+    }
+    for diagnostics in &mut found {
+        diagnostics.sort_by_key(|d| d.line_number);
+    }
+    let diagnostics: Vec<_> = graph
+        .configs
+        .iter()
+        .zip(&found)
+        .flat_map(|(config, found)| found.iter().map(move |d| (config.path.as_path(), d)))
+        .collect();
+    print_diagnostics(diagnostics.iter().copied(), options.format);
+
+    let warnings = check_result(
+        path,
+        "validation",
+        diagnostics.iter().map(|(_, d)| *d),
+        options.deny_warnings,
+    )?;
+    if options.format == Format::Human {
+        let executed = match graph.configs.len() - 1 {
+            0 => String::new(),
+            n => format!(", as are the {} config(s) it executes", n),
+        };
+        match warnings {
+            0 => info!("Config `{}` is valid{}.", path.display(), executed),
+            n => info!(
+                "Config `{}` is valid{}, with {} warning(s).",
+                path.display(),
+                executed,
+                n
+            ),
         }
     }
 
-    let patch_reader = BufReader::new(File::open(patch)?);
-    for (index, line) in patch_reader.lines().enumerate() {
-        let line = line?;
-        let config_item = parser::parse_line(&line).map_err(|e| (e, index))?;
+    Ok(())
+}
 
-        if let Some(item) = config_item {
-            config_set.replace(item);
+/// Runs the lint rules over `target`, printing what they find.
+pub fn lint(target: &Path, options: &LintOptions) -> Result<(), Error> {
+    let document = read_checked_config(target, options.format)?;
+    let path = display_name(target);
+
+    let diagnostics = lint::lint(&document, &options.levels);
+    print_diagnostics(diagnostics.iter().map(|d| (path, d)), options.format);
+
+    let warnings = check_result(path, "linting", &diagnostics, options.deny_warnings)?;
+    if options.format == Format::Human {
+        match warnings {
+            0 => info!("Config `{}` passed linting.", path.display()),
+            n => info!(
+                "Config `{}` passed linting, with {} warning(s).",
+                path.display(),
+                n
+            ),
         }
     }
 
-    let mut target_file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(&target)?;
-    for value in config_set {
-        writeln!(target_file, "{}", value)?;
+    Ok(())
+}
+
+pub fn list_lint_rules() {
+    let width = lint::RULES
+        .iter()
+        .map(|rule| rule.name.len())
+        .max()
+        .unwrap_or(0);
+
+    for rule in lint::RULES {
+        let level = match rule.level {
+            lint::Level::Allow => "allow",
+            lint::Level::Warn => "warn",
+            lint::Level::Deny => "deny",
+        };
+        println!(
+            "{:width$}  {:5}  {}",
+            rule.name,
+            level,
+            rule.description,
+            width = width
+        );
+    }
+}
+
+pub fn list_backups(target: &Path) -> Result<(), Error> {
+    let backups = backup::list(target)?;
+    if backups.is_empty() {
+        return Err(Error::NoBackups(target.to_owned()));
     }
 
-    println!(
-        "Successfully patched `{}` onto `{}`.",
-        patch.display(),
-        target.display()
-    );
+    for backup in backups {
+        println!("{}  {}", backup.id, backup.path.display());
+    }
 
     Ok(())
 }
 
-pub fn validate(target: &Path) -> Result<(), Error> {
-    let target_reader = BufReader::new(File::open(target)?);
-    for (index, line) in target_reader.lines().enumerate() {
-        let line = line?;
-        parser::parse_line(&line).map_err(|e| (e, index))?;
+/// Restores the backup of `target` with the given id, or the latest backup if no id is given.
+/// The target need not exist anymore, and is backed up first if it does.
+pub fn restore(target: &Path, id: Option<&str>) -> Result<(), Error> {
+    let mut backups = backup::list(target)?;
+
+    let backup = match id {
+        Some(id) => backups
+            .into_iter()
+            .find(|backup| backup.id == id)
+            .ok_or_else(|| Error::BackupNotFound {
+                target: target.to_owned(),
+                id: id.to_owned(),
+            })?,
+        None => backups
+            .pop()
+            .ok_or_else(|| Error::NoBackups(target.to_owned()))?,
+    };
+
+    if let Some(replaced) = backup::restore(target, &backup)? {
+        info!(
+            "Backed up `{}` to `{}`.",
+            target.display(),
+            replaced.path.display()
+        );
     }
 
-    println!("Config `{}` is valid.", target.display());
+    info!(
+        "Successfully restored `{}` from `{}`.",
+        target.display(),
+        backup.path.display()
+    );
 
     Ok(())
 }
 
-fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, Error> {
-    let mut args = args.into_iter();
+/// Prints the patch which turns `old` into `new`.
+pub fn diff(old: &Path, new: &Path) -> Result<(), Error> {
+    let patch = read_config(old)?.diff(&read_config(new)?);
 
-    let command = args.next().ok_or_else(|| Error::NoCommandSpecified)?;
+    print!("{}", patch);
 
-    let command = match &command[..] {
-        "patch" => {
-            let target_path = args
-                .next()
-                .ok_or_else(|| Error::MissingArgument("target"))?;
-            let target = Path::new(&target_path)
-                .canonicalize()
-                .map_err(|_| Error::FileNotFound(target_path))?;
+    Ok(())
+}
 
-            let patch_path = args.next().ok_or_else(|| Error::MissingArgument("patch"))?;
-            let patch = Path::new(&patch_path)
-                .canonicalize()
-                .map_err(|_| Error::FileNotFound(patch_path))?;
+/// Prints the merge of the changes made to `base` in `ours` and `theirs`, failing if there are
+/// conflicting changes.
+pub fn merge(base: &Path, ours: &Path, theirs: &Path) -> Result<(), Error> {
+    let merge = merge_configs(
+        base,
+        ours,
+        theirs,
+        (
+            &display_name(ours).display().to_string(),
+            &display_name(theirs).display().to_string(),
+        ),
+    )?;
 
-            Command::Patch { target, patch }
-        }
-        "validate" => {
-            let target_path = args
-                .next()
-                .ok_or_else(|| Error::MissingArgument("target"))?;
-            let target = Path::new(&target_path)
-                .canonicalize()
-                .map_err(|_| Error::FileNotFound(target_path))?;
-
-            Command::Validate { target }
+    print!("{}", merge.document);
+
+    match merge.conflicts.len() {
+        0 => Ok(()),
+        n => Err(Error::MergeConflicts(n)),
+    }
+}
+
+/// Merges like `merge`, following the contract of a git merge driver invoked as
+/// `csgocfg merge-driver %O %A %B`: the result is written to `ours`, and conflicts are signaled
+/// by failing once the result has been written. If any side does not parse, the whole of both
+/// sides is written as a single conflict, since git takes whatever is in `ours` as the result.
+pub fn merge_driver(base: &Path, ours: &Path, theirs: &Path) -> Result<(), Error> {
+    let merge = match merge_configs(base, ours, theirs, ("ours", "theirs")) {
+        Ok(merge) => merge,
+        Err(error @ Error::InvalidConfig { .. }) => {
+            let conflict = format!(
+                "<<<<<<< ours\n{}=======\n{}>>>>>>> theirs\n",
+                terminated(read_source(ours)?),
+                terminated(read_source(theirs)?)
+            );
+            backup::write_atomic(ours, &conflict)?;
+            return Err(error);
         }
-        _ => Command::Unrecognized(command),
+        Err(error) => return Err(error),
     };
 
-    Ok(command)
+    backup::write_atomic(ours, &merge.document.to_string())?;
+
+    match merge.conflicts.len() {
+        0 => Ok(()),
+        n => Err(Error::MergeConflicts(n)),
+    }
+}
+
+/// Ends `source` with a line ending, unless it is empty.
+fn terminated(mut source: String) -> String {
+    if !source.is_empty() && !source.ends_with('\n') {
+        source.push('\n');
+    }
+    source
+}
+
+fn merge_configs(
+    base: &Path,
+    ours: &Path,
+    theirs: &Path,
+    labels: (&str, &str),
+) -> Result<merge::Merge, Error> {
+    Ok(merge::merge(
+        &read_config(base)?,
+        read_config(ours)?,
+        &read_config(theirs)?,
+        labels,
+    ))
+}
+
+/// Fails if a check found any errors, or any warnings when they are denied, and otherwise
+/// returns the number of warnings.
+fn check_result<'a>(
+    path: &Path,
+    check: &'static str,
+    diagnostics: impl IntoIterator<Item = &'a Diagnostic>,
+    deny_warnings: bool,
+) -> Result<usize, Error> {
+    let (mut errors, mut warnings) = (0, 0);
+    for diagnostic in diagnostics {
+        match diagnostic.severity {
+            Severity::Error => errors += 1,
+            Severity::Warning => warnings += 1,
+        }
+    }
+
+    if errors > 0 || (deny_warnings && warnings > 0) {
+        return Err(Error::CheckFailed {
+            path: path.to_owned(),
+            check,
+            errors,
+            warnings,
+        });
+    }
+
+    Ok(warnings)
+}
+
+/// Prints diagnostics to stderr for people, or all at once to stdout for other tools.
+fn print_diagnostics<'a>(
+    diagnostics: impl IntoIterator<Item = (&'a Path, &'a Diagnostic)>,
+    format: Format,
+) {
+    match format {
+        Format::Human => {
+            for (path, diagnostic) in diagnostics {
+                eprintln!("{}\n", diagnostic.render(path));
+            }
+        }
+        Format::Json => println!("{}", format::json(diagnostics)),
+        Format::Sarif => println!("{}", format::sarif(diagnostics)),
+    }
+}
+
+fn read_config(path: &Path) -> Result<Document, Error> {
+    let source = read_source(path)?;
+    parse_config(path, &source)
+}
+
+/// Reads the config for a check, which prints the errors of a config that does not parse in
+/// the requested format like any other diagnostics.
+fn read_checked_config(path: &Path, format: Format) -> Result<Document, Error> {
+    let result = read_config(path);
+
+    // The human format is left to `report`, which prints the diagnostics of the error
+    if let (Err(Error::InvalidConfig { path, diagnostics }), false) =
+        (&result, format == Format::Human)
+    {
+        print_diagnostics(diagnostics.iter().map(|d| (path.as_path(), d)), format);
+    }
+
+    result
+}
+
+/// Reads the config at `path`, or from stdin if the path is `-`.
+fn read_source(path: &Path) -> Result<String, Error> {
+    verbose!("Reading `{}`.", display_name(path).display());
+
+    if is_standard_stream(path) {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        return Ok(source);
+    }
+
+    Ok(fs::read_to_string(path)?)
+}
+
+fn is_standard_stream(path: &Path) -> bool {
+    path == Path::new(STANDARD_STREAM)
+}
+
+/// Returns the name to show for `path` in messages.
+fn display_name(path: &Path) -> &Path {
+    if is_standard_stream(path) {
+        Path::new("<stdin>")
+    } else {
+        path
+    }
+}
+
+fn parse_config(path: &Path, source: &str) -> Result<Document, Error> {
+    Document::parse(source).map_err(|errors| {
+        let lines: Vec<&str> = document::source_lines(source).collect();
+        let diagnostics = errors
+            .iter()
+            .map(|(error, index)| Diagnostic::from_parse_error(error, *index, lines[*index]))
+            .collect();
+
+        Error::InvalidConfig {
+            path: display_name(path).to_owned(),
+            diagnostics,
+        }
+    })
 }
@@ -0,0 +1,366 @@
+use crate::{
+    config::ConfigItem,
+    cvars,
+    diagnostic::{Diagnostic, Severity},
+    document::Document,
+    parser,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+/// How a lint rule is reported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// A named check over the items of a config.
+pub struct Rule {
+    pub name: &'static str,
+    pub level: Level,
+    pub description: &'static str,
+    check: fn(&Document) -> Vec<Finding<'_>>,
+}
+
+/// A problem found by a rule, on the line with the given zero-based index.
+struct Finding<'a> {
+    index: usize,
+    line: &'a str,
+    span: Range<usize>,
+    message: String,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        name: "duplicate-bind",
+        level: Level::Warn,
+        description: "A key is bound more than once, so only the last bind takes effect",
+        check: duplicate_bind,
+    },
+    Rule {
+        name: "conflicting-cvar",
+        level: Level::Warn,
+        description: "A cvar is set more than once to different values",
+        check: conflicting_cvar,
+    },
+    Rule {
+        name: "unbindall-after-binds",
+        level: Level::Deny,
+        description: "`unbindall` comes after binds, which it removes",
+        check: unbindall_after_binds,
+    },
+    Rule {
+        name: "undefined-alias",
+        level: Level::Warn,
+        description: "A bind runs a command which is neither an alias, a command nor a cvar",
+        check: undefined_alias,
+    },
+];
+
+/// The prefix of a comment which suppresses rules on its line, like
+/// `// csgocfg: allow(duplicate-bind)`.
+const ALLOW_DIRECTIVE: &str = "csgocfg: allow(";
+
+/// Returns the rule with the given name.
+pub fn find(name: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|rule| rule.name == name)
+}
+
+/// Runs every rule which is not allowed over `document`, with `levels` overriding the default
+/// level of rules. Findings on a line with an allow comment for their rule are dropped.
+pub fn lint(document: &Document, levels: &HashMap<&str, Level>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for rule in RULES {
+        let severity = match levels.get(rule.name).unwrap_or(&rule.level) {
+            Level::Allow => continue,
+            Level::Warn => Severity::Warning,
+            Level::Deny => Severity::Error,
+        };
+
+        for finding in (rule.check)(document) {
+            if allowed_rules(finding.line).contains(&rule.name) {
+                continue;
+            }
+
+            let diagnostic = Diagnostic::new(
+                severity,
+                finding.message,
+                finding.index,
+                finding.line,
+                finding.span,
+            );
+            diagnostics.push(diagnostic.with_rule(rule.name));
+        }
+    }
+
+    diagnostics.sort_by_key(|d| (d.line_number, d.span.start));
+    diagnostics
+}
+
+/// Returns the rules allowed by a comment on `line`.
+fn allowed_rules(line: &str) -> Vec<&str> {
+    let comment = match parser::comment(line) {
+        Some(comment) => comment,
+        None => return Vec::new(),
+    };
+
+    comment
+        .find(ALLOW_DIRECTIVE)
+        .map(|index| &comment[index + ALLOW_DIRECTIVE.len()..])
+        .and_then(|rest| rest.find(')').map(|end| &rest[..end]))
+        .map_or_else(Vec::new, |rules| rules.split(',').map(str::trim).collect())
+}
+
+fn duplicate_bind(document: &Document) -> Vec<Finding<'_>> {
+    let mut findings = Vec::new();
+    let mut bound = HashMap::new();
+
+    for (index, line, statement) in document.statements() {
+        if let ConfigItem::Bind(key, _) = &statement.item {
+            match bound.get(&statement.item.key()) {
+                Some(first) => findings.push(Finding {
+                    index,
+                    line,
+                    span: statement.argument_spans(line)[0].clone(),
+                    message: format!("`{}` is already bound on line {}", key.value, first + 1),
+                }),
+                None => {
+                    bound.insert(statement.item.key(), index);
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+fn conflicting_cvar(document: &Document) -> Vec<Finding<'_>> {
+    let mut findings = Vec::new();
+    let mut values: HashMap<String, (&str, usize)> = HashMap::new();
+
+    for (index, line, statement) in document.statements() {
+        if let ConfigItem::Cvar(name, value) = &statement.item {
+            let previous = values.insert(name.to_ascii_lowercase(), (&value.value, index));
+
+            match previous {
+                Some((previous, previous_index)) if previous != value.value => {
+                    findings.push(Finding {
+                        index,
+                        line,
+                        span: statement.argument_spans(line)[0].clone(),
+                        message: format!(
+                            "`{}` was already set to `{}` on line {}",
+                            name,
+                            previous,
+                            previous_index + 1
+                        ),
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    findings
+}
+
+fn unbindall_after_binds(document: &Document) -> Vec<Finding<'_>> {
+    let mut findings = Vec::new();
+    let mut first_bind = None;
+
+    for (index, line, statement) in document.statements() {
+        match &statement.item {
+            ConfigItem::Bind(..) => {
+                first_bind.get_or_insert(index);
+            }
+            ConfigItem::Command { name, .. } if name.eq_ignore_ascii_case("unbindall") => {
+                if let Some(first) = first_bind {
+                    findings.push(Finding {
+                        index,
+                        line,
+                        span: statement.span.clone(),
+                        message: format!(
+                            "`unbindall` removes the binds before it, starting on line {}",
+                            first + 1
+                        ),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    findings
+}
+
+fn undefined_alias(document: &Document) -> Vec<Finding<'_>> {
+    let mut findings = Vec::new();
+
+    // Binds only run their command when pressed, so aliases defined anywhere count
+    let aliases: HashSet<String> = document
+        .items()
+        .filter_map(|item| match item {
+            ConfigItem::Alias(name, _) => Some(name.value.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect();
+    let is_defined = |name: &str| {
+        let name = name.to_ascii_lowercase();
+        aliases.contains(&name) || parser::is_command(&name) || cvars::lookup(&name).is_some()
+    };
+
+    for (index, line, statement) in document.statements() {
+        if let ConfigItem::Bind(_, bind) = &statement.item {
+            // Binds which do not parse are not for this rule to judge
+            let statements = parser::parse_line(&bind.value).unwrap_or_default();
+
+            for name in statements.iter().map(|s| command_name(&s.item)) {
+                if !is_defined(name) {
+                    findings.push(Finding {
+                        index,
+                        line,
+                        span: statement.argument_spans(line)[1].clone(),
+                        message: format!(
+                            "`{}` is not an alias defined in this config, nor a known command",
+                            name
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+/// Returns the name of the command, cvar or alias an item runs.
+fn command_name(item: &ConfigItem) -> &str {
+    match item {
+        ConfigItem::Command { name, .. } | ConfigItem::Cvar(name, _) => name,
+        ConfigItem::Bind(..) => "bind",
+        ConfigItem::Alias(..) => "alias",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParseError;
+
+    /// The rule, line number and message of a diagnostic.
+    type Found = (&'static str, usize, String);
+
+    fn linted(source: &str) -> Result<Vec<Found>, Vec<(ParseError, usize)>> {
+        let diagnostics = lint(&Document::parse(source)?, &HashMap::new());
+        Ok(diagnostics
+            .into_iter()
+            .map(|d| (d.rule.unwrap(), d.line_number, d.message))
+            .collect())
+    }
+
+    #[test]
+    fn test_duplicate_bind() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            linted("bind MOUSE1 +attack\nbind w +forward\nbind mouse1 +attack2\n")?,
+            vec![(
+                "duplicate-bind",
+                3,
+                "`mouse1` is already bound on line 1".to_owned()
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_conflicting_cvar() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(
+            linted("volume 0.5\nvolume \"0.5\"\nsensitivity 2; volume 1\n")?,
+            vec![(
+                "conflicting-cvar",
+                3,
+                "`volume` was already set to `0.5` on line 2".to_owned()
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unbindall_after_binds() -> Result<(), Vec<(ParseError, usize)>> {
+        assert_eq!(linted("unbindall\nbind w +forward\n")?, vec![]);
+        assert_eq!(
+            linted("bind w +forward\nunbindall\n")?,
+            vec![(
+                "unbindall-after-binds",
+                2,
+                "`unbindall` removes the binds before it, starting on line 1".to_owned()
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_undefined_alias() -> Result<(), Vec<(ParseError, usize)>> {
+        let source = "\
+bind space +jumpthrow
+bind f \"use weapon_knife; +lookatweapon\"
+bind x \"+jumpthrow; volume 1\"
+bind v +jumptrhow
+alias +jumpthrow \"+jump; -attack\"
+";
+
+        assert_eq!(
+            linted(source)?,
+            vec![(
+                "undefined-alias",
+                4,
+                "`+jumptrhow` is not an alias defined in this config, nor a known command"
+                    .to_owned()
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_levels_and_suppressions() -> Result<(), Vec<(ParseError, usize)>> {
+        let source = "\
+bind f +use
+bind f +lookatweapon // csgocfg: allow(conflicting-cvar, duplicate-bind)
+bind f +reload // Reload
+unbindall
+";
+        let document = Document::parse(source)?;
+
+        let found: Vec<_> = lint(&document, &HashMap::new())
+            .into_iter()
+            .map(|d| (d.rule.unwrap(), d.line_number, d.severity))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("duplicate-bind", 3, Severity::Warning),
+                ("unbindall-after-binds", 4, Severity::Error)
+            ]
+        );
+
+        let levels = [
+            ("duplicate-bind", Level::Deny),
+            ("unbindall-after-binds", Level::Allow),
+        ];
+        let found: Vec<_> = lint(&document, &levels.iter().copied().collect())
+            .into_iter()
+            .map(|d| (d.rule.unwrap(), d.line_number, d.severity))
+            .collect();
+        assert_eq!(found, vec![("duplicate-bind", 3, Severity::Error)]);
+
+        Ok(())
+    }
+}
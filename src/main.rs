@@ -1,18 +1,6 @@
-use csgocfg::Error;
-
 fn main() {
     if let Err(e) = csgocfg::run() {
-        match e {
-            Error::NoCommandSpecified => {
-                csgocfg::usage();
-            }
-            Error::UnrecognizedCommand(_) | Error::MissingArgument(_) => {
-                eprintln!("{}\n", e);
-                csgocfg::usage();
-            }
-            _ => {
-                eprintln!("{}", e);
-            }
-        }
+        csgocfg::report(&e);
+        std::process::exit(e.exit_code());
     }
 }
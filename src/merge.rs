@@ -0,0 +1,165 @@
+use crate::config::{ConfigItem, ConfigKey};
+use crate::document::Document;
+use std::collections::HashMap;
+
+/// The result of a three-way merge, with any conflicts written as git-style conflict markers at
+/// the end of the document.
+pub struct Merge {
+    pub document: Document,
+    pub conflicts: Vec<ConfigKey>,
+}
+
+/// Merges the changes made to `base` in `ours` and `theirs`, key by key. A key changed on only
+/// one side takes that side's value, while a key changed differently on both sides is a
+/// conflict. The merged document keeps the formatting of `ours`.
+pub fn merge(base: &Document, ours: Document, theirs: &Document, labels: (&str, &str)) -> Merge {
+    let base_map = settings_by_key(base);
+    let theirs_map = settings_by_key(theirs);
+
+    // Keys in the order they appear in ours, followed by any keys new to theirs
+    let mut keys: Vec<ConfigKey> = ours.settings().iter().map(|item| item.key()).collect();
+    for item in theirs.settings() {
+        if !keys.contains(&item.key()) {
+            keys.push(item.key());
+        }
+    }
+
+    let ours_map: HashMap<ConfigKey, ConfigItem> = ours
+        .settings()
+        .into_iter()
+        .map(|item| (item.key(), item.clone()))
+        .collect();
+    let mut document = ours;
+    let mut conflicts = Vec::new();
+
+    // Keys only left in base were removed on both sides, so they need no changes
+    for key in &keys {
+        let base = base_map.get(key).copied();
+        let ours = ours_map.get(key);
+        let theirs = theirs_map.get(key).copied();
+
+        if same(ours, theirs) || same(theirs, base) {
+            continue;
+        }
+
+        if same(ours, base) {
+            match theirs {
+                Some(item) => document.set(item),
+                None => document.remove_keys(std::slice::from_ref(key)),
+            }
+        } else {
+            document.remove_keys(std::slice::from_ref(key));
+            conflicts.push((key.clone(), ours.cloned(), theirs.cloned()));
+        }
+    }
+
+    for (_, ours, theirs) in &conflicts {
+        document.push_text(format!("<<<<<<< {}", labels.0));
+        if let Some(item) = ours {
+            document.push_text(item.to_string());
+        }
+        document.push_text("=======".to_owned());
+        if let Some(item) = theirs {
+            document.push_text(item.to_string());
+        }
+        document.push_text(format!(">>>>>>> {}", labels.1));
+    }
+
+    Merge {
+        document,
+        conflicts: conflicts.into_iter().map(|(key, ..)| key).collect(),
+    }
+}
+
+fn same(a: Option<&ConfigItem>, b: Option<&ConfigItem>) -> bool {
+    a.map(ConfigItem::normalized) == b.map(ConfigItem::normalized)
+}
+
+fn settings_by_key(document: &Document) -> HashMap<ConfigKey, &ConfigItem> {
+    document
+        .settings()
+        .into_iter()
+        .map(|item| (item.key(), item))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParseError;
+
+    fn merged(
+        base: &str,
+        ours: &str,
+        theirs: &str,
+    ) -> Result<(String, usize), Vec<(ParseError, usize)>> {
+        let merge = merge(
+            &Document::parse(base)?,
+            Document::parse(ours)?,
+            &Document::parse(theirs)?,
+            ("ours", "theirs"),
+        );
+        Ok((merge.document.to_string(), merge.conflicts.len()))
+    }
+
+    #[test]
+    fn test_merge_non_overlapping_edits() -> Result<(), Vec<(ParseError, usize)>> {
+        let base = "unbindall\nsensitivity 2\nvolume 0.5\nbind f +lookatweapon\n";
+        let ours = "unbindall\nsensitivity 1.8 // Lower\nvolume 0.5\nbind f +lookatweapon\n";
+        let theirs = "unbindall\nvolume 0.3\nsensitivity 2\nbind mouse4 +voicerecord\n";
+
+        assert_eq!(
+            merged(base, ours, theirs)?,
+            (
+                "unbindall\nsensitivity 1.8 // Lower\nvolume 0.3\nbind mouse4 +voicerecord\n"
+                    .to_owned(),
+                0
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_identical_edits() -> Result<(), Vec<(ParseError, usize)>> {
+        let base = "sensitivity 2\nvolume 0.5\n";
+        let ours = "sensitivity 1.8\n";
+        let theirs = "sensitivity \"1.8\"\n";
+
+        assert_eq!(
+            merged(base, ours, theirs)?,
+            ("sensitivity 1.8\n".to_owned(), 0)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_conflicts() -> Result<(), Vec<(ParseError, usize)>> {
+        let base = "sensitivity 2\nvolume 0.5\nbind f +lookatweapon\n";
+        let ours = "sensitivity 1.8\nvolume 0.5\n";
+        let theirs = "sensitivity 2.2\nvolume 0.5\nbind f +use\n";
+
+        assert_eq!(
+            merged(base, ours, theirs)?,
+            (
+                "\
+volume 0.5
+<<<<<<< ours
+sensitivity 1.8
+=======
+sensitivity 2.2
+>>>>>>> theirs
+<<<<<<< ours
+=======
+bind f +use
+>>>>>>> theirs
+"
+                .to_owned(),
+                2
+            )
+        );
+
+        Ok(())
+    }
+}
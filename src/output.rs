@@ -0,0 +1,117 @@
+use std::{
+    io::IsTerminal,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+/// How much the tool prints besides its actual output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// When to color the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    /// Color the output if it goes to a terminal and `NO_COLOR` is not set
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Error,
+    Warning,
+    /// Line numbers and the gutter of snippets
+    Gutter,
+    Added,
+    Removed,
+    Header,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Prints a status message unless `--quiet` was given.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::output::verbosity() > $crate::output::Verbosity::Quiet {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Prints a message to stderr if `--verbose` was given.
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if $crate::output::verbosity() == $crate::output::Verbosity::Verbose {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub fn init(verbosity: Verbosity, color: ColorChoice) {
+    let color = match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none()
+                && std::io::stdout().is_terminal()
+                && std::io::stderr().is_terminal()
+        }
+    };
+
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+    COLOR.store(color, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+/// Wraps `text` in the escape codes for `style`, if the output is colored.
+pub fn paint(text: &str, style: Style) -> String {
+    if !COLOR.load(Ordering::Relaxed) {
+        return text.to_owned();
+    }
+
+    let code = match style {
+        Style::Error => "1;31",
+        Style::Warning => "1;33",
+        Style::Gutter => "1;34",
+        Style::Added => "32",
+        Style::Removed => "31",
+        Style::Header => "1",
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+/// Colors the lines of a unified diff by whether they were added or removed.
+pub fn paint_diff(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                Some(Style::Header)
+            } else if line.starts_with("@@") {
+                Some(Style::Gutter)
+            } else if line.starts_with('+') {
+                Some(Style::Added)
+            } else if line.starts_with('-') {
+                Some(Style::Removed)
+            } else {
+                None
+            };
+
+            match style {
+                Some(style) => format!("{}\n", paint(line, style)),
+                None => format!("{}\n", line),
+            }
+        })
+        .collect()
+}
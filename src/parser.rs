@@ -1,8 +1,17 @@
-use crate::config::ConfigItem;
+use crate::config::{Arg, ConfigItem};
+use std::ops::Range;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
-pub enum ParseError {
+#[error("{kind}")]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The byte range of the offending input within its line
+    pub span: Range<usize>,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParseErrorKind {
     #[error("invalid identifier `{0}`")]
     InvalidIdentifier(String),
     #[error("invalid string literal (expected `\"...\"`, found `{0}`)")]
@@ -11,10 +20,213 @@ pub enum ParseError {
     UnexpectedEndOfLine(String),
 }
 
-pub fn parse_line(line: &str) -> Result<Option<ConfigItem>, ParseError> {
-    // A line looks like
-    // command {argument 1} {argument 2} [COMMENT]
-    // where the number of arguments can be either 0, 1, or 2.
+impl ParseErrorKind {
+    /// A stable name for the kind of error, like `invalid-identifier`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            ParseErrorKind::InvalidIdentifier(_) => "invalid-identifier",
+            ParseErrorKind::InvalidStringLiteral(_) => "invalid-string-literal",
+            ParseErrorKind::UnexpectedEndOfLine(_) => "unexpected-end-of-line",
+        }
+    }
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, span: Range<usize>) -> Self {
+        ParseError { kind, span }
+    }
+
+    fn offset(self, offset: usize) -> Self {
+        let span = self.span.start + offset..self.span.end + offset;
+        ParseError { span, ..self }
+    }
+}
+
+/// A parsed statement along with the byte range it occupies in its line, excluding surrounding
+/// whitespace and comments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub item: ConfigItem,
+    pub span: Range<usize>,
+}
+
+impl Statement {
+    /// Returns the byte ranges of the arguments of the statement within `line`, the line it was
+    /// parsed from.
+    pub fn argument_spans(&self, line: &str) -> Vec<Range<usize>> {
+        let start = self.span.start;
+        argument_spans(&line[self.span.clone()])
+            .into_iter()
+            .map(|span| span.start + start..span.end + start)
+            .collect()
+    }
+}
+
+/// Parses every statement on a line, with spans relative to the start of the line.
+pub fn parse_line(line: &str) -> Result<Vec<Statement>, ParseError> {
+    let mut statements = Vec::new();
+
+    for (offset, input) in split_statements(line) {
+        let statement = parse_statement(input).map_err(|e| e.offset(offset))?;
+        if let Some(mut statement) = statement {
+            statement.span = statement.span.start + offset..statement.span.end + offset;
+            statements.push(statement);
+        }
+    }
+
+    Ok(statements)
+}
+
+/// Splits a line into its `;`-separated statements, ignoring separators within quotes and
+/// stopping at a comment. Each statement is trimmed of whitespace and returned along with its
+/// byte offset into the line.
+pub fn split_statements(line: &str) -> Vec<(usize, &str)> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+
+    let mut push = |start: usize, end: usize| {
+        let input = ignore_whitespace(&line[start..end]);
+        let offset = end - input.len();
+        let input = input.trim_end();
+        if !input.is_empty() {
+            statements.push((offset, input));
+        }
+    };
+
+    for (index, c) in line.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && c == ';' {
+            push(start, index);
+            start = index + 1;
+        } else if !in_quotes && is_comment(&line[index..]) {
+            push(start, index);
+            return statements;
+        }
+    }
+    push(start, line.len());
+
+    statements
+}
+
+/// Returns the text of the comment ending `line`, after its `//`, ignoring any `//` within
+/// quotes.
+pub fn comment(line: &str) -> Option<&str> {
+    let mut in_quotes = false;
+
+    for (index, c) in line.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && is_comment(&line[index..]) {
+            return Some(&line[index + 2..]);
+        }
+    }
+
+    None
+}
+
+/// Returns whether `name` is a command of the game rather than a cvar.
+pub fn is_command(name: &str) -> bool {
+    COMMANDS.binary_search(&name).is_ok()
+}
+
+/// Commands of the game which configs commonly run, sorted. A statement running one of them with
+/// a single argument, like `use weapon_knife`, is a command rather than a cvar being set.
+const COMMANDS: &[&str] = &[
+    "+attack",
+    "+attack2",
+    "+back",
+    "+duck",
+    "+forward",
+    "+jump",
+    "+left",
+    "+lookatweapon",
+    "+moveleft",
+    "+moveright",
+    "+radialradio",
+    "+radialradio2",
+    "+radialradio3",
+    "+reload",
+    "+right",
+    "+score",
+    "+showscores",
+    "+speed",
+    "+spray_menu",
+    "+use",
+    "+voicerecord",
+    "alias",
+    "autobuy",
+    "bind",
+    "bot_kick",
+    "buy",
+    "buymenu",
+    "buyrandom",
+    "callvote",
+    "cancelselect",
+    "cl_clearhinthistory",
+    "clear",
+    "connect",
+    "disconnect",
+    "drop",
+    "echo",
+    "exec",
+    "explode",
+    "give",
+    "god",
+    "host_writeconfig",
+    "incrementvar",
+    "invnext",
+    "invprev",
+    "jpeg",
+    "kick",
+    "kill",
+    "lastinv",
+    "map",
+    "messagemode",
+    "messagemode2",
+    "noclip",
+    "play",
+    "playdemo",
+    "player_ping",
+    "quit",
+    "radio",
+    "radio1",
+    "radio2",
+    "radio3",
+    "rebuy",
+    "record",
+    "retry",
+    "say",
+    "say_team",
+    "screenshot",
+    "show_loadout_toggle",
+    "slot1",
+    "slot10",
+    "slot11",
+    "slot2",
+    "slot3",
+    "slot4",
+    "slot5",
+    "slot6",
+    "slot7",
+    "slot8",
+    "slot9",
+    "status",
+    "teammenu",
+    "toggle",
+    "toggleconsole",
+    "unbind",
+    "unbindall",
+    "use",
+    "vote",
+];
+
+fn parse_statement(line: &str) -> Result<Option<Statement>, ParseError> {
+    // A statement looks like
+    // command {argument 1} {argument 2} ... [COMMENT]
+    // where there can be any number of arguments, and each argument is either a quoted string
+    // or a bare token ending at whitespace, `;` or `//`.
     // Whitespace is optional and can appear zero or more times between the tokens above.
 
     // [COMMENT]
@@ -23,43 +235,111 @@ pub fn parse_line(line: &str) -> Result<Option<ConfigItem>, ParseError> {
         return Ok(None);
     }
 
-    // command [COMMENT]
-    let (input, cmd) =
-        identifier(input).map_err(|i| ParseError::InvalidIdentifier(i.to_owned()))?;
-    let input = ignore_whitespace(input);
-    if is_empty_or_comment(input) {
-        return Ok(Some(ConfigItem::Command(cmd.to_owned())));
-    }
+    // The statement spans from the command to the end of its last token
+    let start = line.len() - input.len();
 
-    // command "argument 1" [COMMENT]
-    let (input, arg1) =
-        string_literal(input).map_err(|i| ParseError::InvalidStringLiteral(i.to_owned()))?;
-    let input = ignore_whitespace(input);
-    if is_empty_or_comment(input) {
-        return Ok(Some(ConfigItem::Cvar(cmd.to_owned(), arg1.to_owned())));
-    }
+    // command
+    let (input, cmd) = identifier(input).map_err(|i| {
+        let span = start..start + bare_token(i).map_or(i.len(), |(_, token)| token.len());
+        ParseError::new(ParseErrorKind::InvalidIdentifier(i.to_owned()), span)
+    })?;
+    let end = line.len() - input.len();
+
+    // {argument 1} {argument 2} ... [COMMENT]
+    let (spans, args): (Vec<_>, Vec<_>) = arguments(line, input)?.into_iter().unzip();
+    let end = spans.last().map_or(end, |span| span.end);
+
+    // Binds, aliases and cvars are special-cased, everything else is a generic command
+    let item = match (cmd, &args[..]) {
+        ("bind", [key, bind]) => ConfigItem::Bind(key.clone(), bind.clone()),
+        ("alias", [name, body]) => {
+            let body_start = spans[1].start + if body.quoted { 1 } else { 0 };
+            let body = parse_line(&body.value).map_err(|e| e.offset(body_start))?;
+            let body = body.into_iter().map(|s| s.item).collect();
+            ConfigItem::Alias(name.clone(), body)
+        }
+        (cvar, [value]) if !is_command(cvar) => ConfigItem::Cvar(cvar.to_owned(), value.clone()),
+        _ => ConfigItem::Command {
+            name: cmd.to_owned(),
+            args,
+        },
+    };
 
-    // command "argument 1" "argument 2" [COMMENT]
-    let (input, arg2) =
-        string_literal(input).map_err(|i| ParseError::InvalidStringLiteral(i.to_owned()))?;
-    let input = ignore_whitespace(input);
-    if is_empty_or_comment(input) && cmd == "bind" {
-        return Ok(Some(ConfigItem::Bind(arg1.to_owned(), arg2.to_owned())));
+    Ok(Some(Statement {
+        item,
+        span: start..end,
+    }))
+}
+
+/// Parses the arguments following the command of a statement, along with the byte range each one
+/// occupies in `line`, where `input` is the rest of `line` after the command.
+fn arguments(line: &str, mut input: &str) -> Result<Vec<(Range<usize>, Arg)>, ParseError> {
+    let mut args = Vec::new();
+
+    loop {
+        input = ignore_whitespace(input);
+        if is_empty_or_comment(input) {
+            return Ok(args);
+        }
+
+        let start = line.len() - input.len();
+        let (rest, arg) = argument(input).map_err(|e| e.offset(start))?;
+        input = rest;
+        args.push((start..line.len() - input.len(), arg));
     }
+}
+
+/// Returns the byte ranges of the arguments of a statement which has already been parsed,
+/// relative to the start of `statement`.
+fn argument_spans(statement: &str) -> Vec<Range<usize>> {
+    let input = ignore_whitespace(statement);
+    let args = identifier(input)
+        .ok()
+        .and_then(|(input, _)| arguments(statement, input).ok());
 
-    Err(ParseError::UnexpectedEndOfLine(input.to_owned()))
+    args.into_iter().flatten().map(|(span, _)| span).collect()
 }
 
 type ParseResult<'a, Output> = Result<(&'a str, Output), &'a str>;
 
 fn ignore_whitespace(input: &str) -> &str {
-    match input.chars().position(|c| c != ' ') {
-        Some(index) => &input[index..],
-        _ => &input[input.len()..],
+    input.trim_start()
+}
+
+/// Parses a quoted or bare argument. Errors span from the start of `input` to its end.
+fn argument(input: &str) -> Result<(&str, Arg), ParseError> {
+    if input.starts_with('"') {
+        string_literal(input)
+            .map(|(i, value)| (i, Arg::quoted(value)))
+            .map_err(|i| {
+                let kind = ParseErrorKind::InvalidStringLiteral(i.to_owned());
+                ParseError::new(kind, 0..input.len())
+            })
+    } else {
+        bare_token(input)
+            .map(|(i, value)| (i, Arg::unquoted(value)))
+            .map_err(|i| {
+                let kind = ParseErrorKind::UnexpectedEndOfLine(i.to_owned());
+                ParseError::new(kind, 0..input.len())
+            })
+    }
+}
+
+fn bare_token(input: &str) -> ParseResult<'_, &str> {
+    let end = input
+        .char_indices()
+        .find(|&(index, c)| {
+            c.is_whitespace() || c == ';' || c == '"' || is_empty_or_comment(&input[index..])
+        })
+        .map_or(input.len(), |(index, _)| index);
+
+    match end {
+        0 => Err(input),
+        _ => Ok((&input[end..], &input[..end])),
     }
 }
 
-fn string_literal(input: &str) -> ParseResult<&str> {
+fn string_literal(input: &str) -> ParseResult<'_, &str> {
     let match_quote = match_literal("\"");
     let match_until_quote = match_until_char('"');
 
@@ -69,31 +349,35 @@ fn string_literal(input: &str) -> ParseResult<&str> {
 }
 
 fn match_until_char(expected: char) -> impl Fn(&str) -> ParseResult<&str> {
-    move |input: &str| match input.chars().position(|c| c == expected) {
+    move |input: &str| match input.find(expected) {
         Some(index) => Ok((&input[index..], &input[..index])),
         _ => Err(input),
     }
 }
 
-fn identifier(input: &str) -> ParseResult<&str> {
-    let mut chars = input.chars();
+fn identifier(input: &str) -> ParseResult<'_, &str> {
+    let mut chars = input.char_indices();
 
     if chars
         .next()
-        .filter(|&c| c.is_alphabetic() || c == '_' || c == '@')
+        .filter(|&(_, c)| c.is_alphabetic() || c == '_' || c == '@' || c == '+' || c == '-')
         .is_none()
     {
         return Err(input);
     };
 
-    match chars.position(|c| !(c.is_alphanumeric() || c == '_' || c == '@')) {
-        Some(index) => Ok((&input[index + 1..], &input[..index + 1])),
+    match chars.find(|&(_, c)| !(c.is_alphanumeric() || c == '_' || c == '@')) {
+        Some((index, _)) => Ok((&input[index..], &input[..index])),
         None => Ok((&input[input.len()..], input)),
     }
 }
 
 fn is_empty_or_comment(input: &str) -> bool {
-    input.is_empty() || match_literal("//")(input).is_ok()
+    input.is_empty() || is_comment(input)
+}
+
+fn is_comment(input: &str) -> bool {
+    match_literal("//")(input).is_ok()
 }
 
 fn match_literal(expected: &'static str) -> impl Fn(&str) -> ParseResult<&'static str> {
@@ -114,21 +398,49 @@ fn match_literal(expected: &'static str) -> impl Fn(&str) -> ParseResult<&'stati
 mod tests {
     use super::*;
 
+    fn command(name: &str, args: Vec<Arg>) -> ConfigItem {
+        ConfigItem::Command {
+            name: name.to_owned(),
+            args,
+        }
+    }
+
+    fn parse_item(line: &str) -> Result<Option<ConfigItem>, ParseError> {
+        parse_statement(line).map(|statement| statement.map(|s| s.item))
+    }
+
     #[test]
     fn test_bind_parsing() -> Result<(), ParseError> {
         assert_eq!(
-            parse_line(r#"bind "enter" "slot1""#)?,
-            Some(ConfigItem::Bind("enter".to_owned(), "slot1".to_owned()))
+            parse_item(r#"bind "enter" "slot1""#)?,
+            Some(ConfigItem::Bind(Arg::quoted("enter"), Arg::quoted("slot1")))
+        );
+        assert_eq!(
+            parse_item(r#"bind"mouse1""+attack""#)?,
+            Some(ConfigItem::Bind(
+                Arg::quoted("mouse1"),
+                Arg::quoted("+attack")
+            ))
+        );
+        assert_eq!(
+            parse_item(r#"  bind    "4" "slot4"     // Comment  "#)?,
+            Some(ConfigItem::Bind(Arg::quoted("4"), Arg::quoted("slot4")))
         );
         assert_eq!(
-            parse_line(r#"bind"mouse1""+attack""#)?,
-            Some(ConfigItem::Bind("mouse1".to_owned(), "+attack".to_owned()))
+            parse_item(r#"bind a +moveleft// Strafe"#)?,
+            Some(ConfigItem::Bind(
+                Arg::unquoted("a"),
+                Arg::unquoted("+moveleft")
+            ))
         );
         assert_eq!(
-            parse_line(r#"  bind    "4" "slot4"     // Comment  "#)?,
-            Some(ConfigItem::Bind("4".to_owned(), "slot4".to_owned()))
+            parse_item(r#"bind "mwheeldown" +jump"#)?,
+            Some(ConfigItem::Bind(
+                Arg::quoted("mwheeldown"),
+                Arg::unquoted("+jump")
+            ))
         );
-        assert!(parse_line(r#"bind "a" "non-ending string"#).is_err(),);
+        assert!(parse_item(r#"bind "a" "non-ending string"#).is_err(),);
 
         Ok(())
     }
@@ -136,14 +448,24 @@ mod tests {
     #[test]
     fn test_cvar_parsing() -> Result<(), ParseError> {
         assert_eq!(
-            parse_line(r#"sensitivity "1.5""#)?,
-            Some(ConfigItem::Cvar("sensitivity".to_owned(), "1.5".to_owned()))
+            parse_item(r#"sensitivity "1.5""#)?,
+            Some(ConfigItem::Cvar(
+                "sensitivity".to_owned(),
+                Arg::quoted("1.5")
+            ))
+        );
+        assert_eq!(
+            parse_item(r#"   volume     "0.5"  // Comment here  "#)?,
+            Some(ConfigItem::Cvar("volume".to_owned(), Arg::quoted("0.5")))
         );
         assert_eq!(
-            parse_line(r#"   volume     "0.5"  // Comment here  "#)?,
-            Some(ConfigItem::Cvar("volume".to_owned(), "0.5".to_owned()))
+            parse_item(r#"hud_scaling 0.8"#)?,
+            Some(ConfigItem::Cvar(
+                "hud_scaling".to_owned(),
+                Arg::unquoted("0.8")
+            ))
         );
-        assert!(parse_line(r#"hud_scaling 0.8"#).is_err());
+        assert!(parse_item(r#"fps_max "300"#).is_err());
 
         Ok(())
     }
@@ -151,14 +473,173 @@ mod tests {
     #[test]
     fn test_cmd_parsing() -> Result<(), ParseError> {
         assert_eq!(
-            parse_line(r#"  unbindall    "#)?,
-            Some(ConfigItem::Command("unbindall".to_owned()))
+            parse_item(r#"  unbindall    "#)?,
+            Some(command("unbindall", vec![]))
+        );
+        assert_eq!(
+            parse_item(r#"disconnect   //Comment Foo  "#)?,
+            Some(command("disconnect", vec![]))
+        );
+        assert_eq!(
+            parse_item(r#"use weapon_knife"#)?,
+            Some(command("use", vec![Arg::unquoted("weapon_knife")]))
+        );
+        assert!(parse_item(r#"1quit"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_argument_parsing() -> Result<(), ParseError> {
+        assert_eq!(
+            parse_item(r#"incrementvar cl_radar_scale 0.25 1.0 0.05"#)?,
+            Some(command(
+                "incrementvar",
+                vec![
+                    Arg::unquoted("cl_radar_scale"),
+                    Arg::unquoted("0.25"),
+                    Arg::unquoted("1.0"),
+                    Arg::unquoted("0.05")
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_item(r#"exec "binds/nades""#)?,
+            Some(command("exec", vec![Arg::quoted("binds/nades")]))
+        );
+        assert_eq!(
+            parse_item(r#"echo Config loaded "successfully" // Done"#)?,
+            Some(command(
+                "echo",
+                vec![
+                    Arg::unquoted("Config"),
+                    Arg::unquoted("loaded"),
+                    Arg::quoted("successfully")
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_item(r#"bind "f""#)?,
+            Some(command("bind", vec![Arg::quoted("f")]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let error_span = |line| parse_line(line).unwrap_err().span;
+
+        assert_eq!(error_span(r#"  1quit now"#), 2..7);
+        assert_eq!(error_span(r#"volume "0.5"#), 7..11);
+        assert_eq!(error_span(r#"unbindall; bind "w" "+forward"#), 20..29);
+        assert_eq!(error_span(r#"alias "jt" "+jump; 1quit""#), 19..24);
+    }
+
+    #[test]
+    fn test_whitespace_handling() -> Result<(), ParseError> {
+        assert_eq!(
+            parse_item("\tbind\t\"w\"\t+forward\t// Comment\r")?,
+            Some(ConfigItem::Bind(
+                Arg::quoted("w"),
+                Arg::unquoted("+forward")
+            ))
+        );
+        assert_eq!(
+            parse_item("volume\u{a0}0.5\r")?,
+            Some(ConfigItem::Cvar("volume".to_owned(), Arg::unquoted("0.5")))
+        );
+        assert_eq!(
+            parse_item("echo \"Ünïcödé\" ok")?,
+            Some(command(
+                "echo",
+                vec![Arg::quoted("Ünïcödé"), Arg::unquoted("ok")]
+            ))
+        );
+        assert_eq!(
+            split_statements("\t unbindall \t;\tquit\r"),
+            vec![(2, "unbindall"), (15, "quit")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_span() -> Result<(), ParseError> {
+        let line = r#"  bind "4" "slot4"   // Comment"#;
+        let statements = parse_line(line)?;
+        assert_eq!(&line[statements[0].span.clone()], r#"bind "4" "slot4""#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_splitting() {
+        assert_eq!(
+            split_statements(r#"unbindall; bind "w" "+forward";cl_radar_scale 0.4 // a; b"#),
+            vec![
+                (0, "unbindall"),
+                (11, r#"bind "w" "+forward""#),
+                (31, "cl_radar_scale 0.4")
+            ]
+        );
+        assert_eq!(
+            split_statements(r#"alias "jt" "+jump; -attack";; "#),
+            vec![(0, r#"alias "jt" "+jump; -attack""#)]
+        );
+        assert_eq!(split_statements("  // Comment; still comment"), vec![]);
+    }
+
+    #[test]
+    fn test_comment() {
+        assert_eq!(comment(r#"bind "w" "+forward" // Move"#), Some(" Move"));
+        assert_eq!(comment(r#"echo "http://example.com""#), None);
+        assert_eq!(comment("// a // b"), Some(" a // b"));
+    }
+
+    #[test]
+    fn test_multi_statement_parsing() -> Result<(), ParseError> {
+        let line = r#"unbindall; bind "w" "+forward"; cl_radar_scale 0.4"#;
+        let statements = parse_line(line)?;
+        assert_eq!(
+            statements.into_iter().map(|s| s.item).collect::<Vec<_>>(),
+            vec![
+                command("unbindall", vec![]),
+                ConfigItem::Bind(Arg::quoted("w"), Arg::quoted("+forward")),
+                ConfigItem::Cvar("cl_radar_scale".to_owned(), Arg::unquoted("0.4"))
+            ]
+        );
+        assert!(parse_line(r#"unbindall; bind "w""#).is_ok());
+        assert!(parse_line(r#"unbindall; 1quit"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_alias_parsing() -> Result<(), ParseError> {
+        assert_eq!(
+            parse_item(r#"alias "jumpthrow" "+jump; -attack; -attack2""#)?,
+            Some(ConfigItem::Alias(
+                Arg::quoted("jumpthrow"),
+                vec![
+                    command("+jump", vec![]),
+                    command("-attack", vec![]),
+                    command("-attack2", vec![])
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_item(r#"alias +jt "+jump""#)?,
+            Some(ConfigItem::Alias(
+                Arg::unquoted("+jt"),
+                vec![command("+jump", vec![])]
+            ))
         );
         assert_eq!(
-            parse_line(r#"disconnect   //Comment Foo  "#)?,
-            Some(ConfigItem::Command("disconnect".to_owned()))
+            parse_item(r#"alias "nothing" """#)?,
+            Some(ConfigItem::Alias(Arg::quoted("nothing"), vec![]))
         );
-        assert!(parse_line(r#"1quit"#).is_err());
+        assert!(parse_item(r#"alias "broken" "1quit""#).is_err());
 
         Ok(())
     }
@@ -0,0 +1,253 @@
+use crate::{
+    config::{Arg, ConfigItem},
+    cvars::{self, Flag, Invalid},
+    diagnostic::{Diagnostic, Severity},
+    document::Document,
+    keys,
+};
+use std::ops::Range;
+
+/// Checks the cvars and bind keys of a config beyond its syntax, returning a diagnostic for
+/// every problem in the order they appear.
+pub fn validate(document: &Document) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // Cheat cvars are only worth a warning until the config itself enables cheats
+    let mut cheats = false;
+
+    for (index, line, statement) in document.statements() {
+        let start = statement.span.start;
+        let args = statement.argument_spans(line);
+        let mut problems = Vec::new();
+
+        match &statement.item {
+            ConfigItem::Cvar(name, value) => {
+                let name_span = start..start + name.len();
+                check_cvar(
+                    name,
+                    value,
+                    name_span,
+                    args[0].clone(),
+                    cheats,
+                    &mut problems,
+                );
+
+                if name.eq_ignore_ascii_case("sv_cheats") {
+                    cheats = value.value != "0";
+                }
+            }
+            ConfigItem::Bind(key, _) => check_key(&key.value, args[0].clone(), &mut problems),
+            ConfigItem::Command { name, args: values } if name == "unbind" && values.len() == 1 => {
+                check_key(&values[0].value, args[0].clone(), &mut problems)
+            }
+            // Statements within an alias body are pointed out by the body as a whole
+            ConfigItem::Alias(_, body) => {
+                for (name, value) in alias_cvars(body) {
+                    let span = args[1].clone();
+                    check_cvar(name, value, span.clone(), span, cheats, &mut problems);
+                }
+            }
+            _ => {}
+        }
+
+        for (severity, rule, message, span) in problems {
+            let diagnostic = Diagnostic::new(severity, message, index, line, span);
+            diagnostics.push(diagnostic.with_rule(rule));
+        }
+    }
+
+    diagnostics
+}
+
+/// The severity, rule id, message and span of a problem.
+type Problem = (Severity, &'static str, String, Range<usize>);
+
+fn check_cvar(
+    name: &str,
+    value: &Arg,
+    name_span: Range<usize>,
+    value_span: Range<usize>,
+    cheats: bool,
+    problems: &mut Vec<Problem>,
+) {
+    let cvar = match cvars::lookup(name) {
+        Some(cvar) => cvar,
+        None => {
+            let message = format!("unknown cvar `{}`", name);
+            problems.push((Severity::Warning, "unknown-cvar", message, name_span));
+            return;
+        }
+    };
+
+    match cvar.check(&value.value) {
+        Ok(()) => {}
+        Err(Invalid::TypeMismatch) => {
+            let message = format!("`{}` expects {}, found `{}`", name, cvar.kind, value.value);
+            problems.push((Severity::Error, "type-mismatch", message, value_span));
+        }
+        Err(Invalid::OutOfRange) => {
+            let message = format!(
+                "`{}` must be {} (default {}), found `{}`",
+                name,
+                cvar.range(),
+                cvar.default,
+                value.value
+            );
+            problems.push((Severity::Error, "out-of-range", message, value_span));
+        }
+    }
+
+    if cvar.has_flag(Flag::Cheat) && !cheats {
+        let message = format!(
+            "`{}` is a cheat, so it has no effect unless `sv_cheats` is 1",
+            name
+        );
+        problems.push((Severity::Warning, "cheat-cvar", message, name_span));
+    } else if cvar.has_flag(Flag::DevOnly) {
+        let message = format!("`{}` is only available in development builds", name);
+        problems.push((Severity::Warning, "devonly-cvar", message, name_span));
+    }
+}
+
+fn check_key(name: &str, span: Range<usize>, problems: &mut Vec<Problem>) {
+    if keys::canonical(name).is_some() {
+        return;
+    }
+
+    let message = match keys::suggest(name) {
+        Some(key) => format!("unknown key `{}`, did you mean `{}`?", name, key),
+        None => format!("unknown key `{}`", name),
+    };
+    problems.push((Severity::Error, "unknown-key", message, span));
+}
+
+/// Returns every cvar set within an alias body, including those of nested aliases.
+fn alias_cvars(body: &[ConfigItem]) -> Vec<(&str, &Arg)> {
+    body.iter()
+        .flat_map(|item| match item {
+            ConfigItem::Cvar(name, value) => vec![(name.as_str(), value)],
+            ConfigItem::Alias(_, body) => alias_cvars(body),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParseError;
+
+    /// The severity, message, line number and span of a diagnostic.
+    type Found = (Severity, String, usize, Range<usize>);
+
+    fn validated(source: &str) -> Result<Vec<Found>, Vec<(ParseError, usize)>> {
+        let diagnostics = validate(&Document::parse(source)?);
+        Ok(diagnostics
+            .into_iter()
+            .map(|d| (d.severity, d.message, d.line_number, d.span))
+            .collect())
+    }
+
+    #[test]
+    fn test_valid_cvars() -> Result<(), Vec<(ParseError, usize)>> {
+        let source =
+            "sensitivity \"1.8\"\nfps_max 0; cl_crosshaircolor 5 // Cyan\nname \"s1mple\"\n";
+        assert_eq!(validated(source)?, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_commands_with_one_argument() -> Result<(), Vec<(ParseError, usize)>> {
+        let source = "use weapon_knife\nslot1; buy ak47\nsay gl\n";
+        assert_eq!(validated(source)?, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_cvars() -> Result<(), Vec<(ParseError, usize)>> {
+        let source = "sensitivity \"abc\"\nvolume 0.5; fps_max \"-5\"\nsensitivty 2\n";
+
+        assert_eq!(
+            validated(source)?,
+            vec![
+                (
+                    Severity::Error,
+                    "`sensitivity` expects a number, found `abc`".to_owned(),
+                    1,
+                    12..17
+                ),
+                (
+                    Severity::Error,
+                    "`fps_max` must be at least 0 (default 300), found `-5`".to_owned(),
+                    2,
+                    20..24
+                ),
+                (
+                    Severity::Warning,
+                    "unknown cvar `sensitivty`".to_owned(),
+                    3,
+                    0..10
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cheats() -> Result<(), Vec<(ParseError, usize)>> {
+        let source = "sv_grenade_trajectory 1\nsv_cheats 1\nsv_showimpacts 1\n";
+        let diagnostics = validated(source)?;
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, Severity::Warning);
+        assert_eq!(diagnostics[0].2, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_keys() -> Result<(), Vec<(ParseError, usize)>> {
+        let source =
+            "bind MOUSE1 +attack; bind \"mouse_4\" +voicerecord\nunbind kp_ent\nbind rdstj x\n";
+
+        assert_eq!(
+            validated(source)?,
+            vec![
+                (
+                    Severity::Error,
+                    "unknown key `mouse_4`, did you mean `mouse4`?".to_owned(),
+                    1,
+                    26..35
+                ),
+                (
+                    Severity::Error,
+                    "unknown key `kp_ent`, did you mean `kp_enter`?".to_owned(),
+                    2,
+                    7..13
+                ),
+                (Severity::Error, "unknown key `rdstj`".to_owned(), 3, 5..10),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_alias_body() -> Result<(), Vec<(ParseError, usize)>> {
+        let source = "alias +scope \"sensitivity fast; +attack2\"\n";
+
+        assert_eq!(
+            validated(source)?,
+            vec![(
+                Severity::Error,
+                "`sensitivity` expects a number, found `fast`".to_owned(),
+                1,
+                13..41
+            )]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,445 @@
+use csgocfg::exit_code;
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("csgocfg-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn csgocfg(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_csgocfg"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_success() {
+    let dir = temp_dir("success");
+    fs::write(dir.join("autoexec.cfg"), "volume 0.5\n").unwrap();
+
+    let output = csgocfg(&dir, &["validate", "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert!(stdout(&output).contains("is valid"));
+
+    let output = csgocfg(&dir, &["--quiet", "validate", "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(stdout(&output), "");
+
+    let output = csgocfg(&dir, &["--version"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert!(stdout(&output).starts_with("csgocfg "));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_usage_errors() {
+    let dir = temp_dir("usage");
+    fs::write(dir.join("autoexec.cfg"), "volume 0.5\n").unwrap();
+
+    for args in &[
+        &[][..],
+        &["frobnicate"],
+        &["validate"],
+        &["validate", "autoexec.cfg", "autoexec.cfg"],
+        &["validate", "--frobnicate", "autoexec.cfg"],
+        &["patch", "--backups", "many", "autoexec.cfg", "autoexec.cfg"],
+        &["--color", "sometimes", "validate", "autoexec.cfg"],
+    ] {
+        let output = csgocfg(&dir, args);
+        assert_eq!(output.status.code(), Some(exit_code::USAGE), "{:?}", args);
+        assert!(stderr(&output).contains("usage"), "{:?}", args);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_io_errors() {
+    let dir = temp_dir("io");
+    fs::write(dir.join("autoexec.cfg"), "volume 0.5\n").unwrap();
+
+    let output = csgocfg(&dir, &["validate", "missing.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::IO));
+    assert_eq!(stderr(&output), "error: file not found `missing.cfg`\n");
+
+    let output = csgocfg(&dir, &["restore", "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::IO));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_invalid_config() {
+    let dir = temp_dir("invalid");
+    fs::write(dir.join("autoexec.cfg"), "volume 0.5\nbind \"w\n").unwrap();
+    fs::write(dir.join("patch.cfg"), "volume 1\n").unwrap();
+
+    for args in &[
+        &["validate", "autoexec.cfg"][..],
+        &["patch", "autoexec.cfg", "patch.cfg"],
+        &["patch", "patch.cfg", "autoexec.cfg"],
+    ] {
+        let output = csgocfg(&dir, args);
+        assert_eq!(
+            output.status.code(),
+            Some(exit_code::INVALID_CONFIG),
+            "{:?}",
+            args
+        );
+        assert!(stderr(&output).contains(":2:6\n"), "{:?}", args);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_failed_checks() {
+    let dir = temp_dir("checks");
+    fs::write(dir.join("base.cfg"), "volume 0.5\n").unwrap();
+    fs::write(dir.join("ours.cfg"), "volume 0.3\n").unwrap();
+    fs::write(dir.join("theirs.cfg"), "volume 1\n").unwrap();
+
+    let output = csgocfg(&dir, &["patch", "--check", "base.cfg", "theirs.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    assert_eq!(
+        fs::read_to_string(dir.join("base.cfg")).unwrap(),
+        "volume 0.5\n"
+    );
+
+    let output = csgocfg(&dir, &["patch", "--check", "base.cfg", "base.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+
+    // A config without a final line ending is not modified by a patch which changes nothing
+    fs::write(dir.join("no-eol.cfg"), "volume 0.5").unwrap();
+    fs::write(dir.join("empty.cfg"), "").unwrap();
+    let output = csgocfg(&dir, &["patch", "--check", "no-eol.cfg", "empty.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    let output = csgocfg(&dir, &["patch", "no-eol.cfg", "base.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert!(stdout(&output).contains("already up to date"));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 5);
+
+    fs::write(dir.join("quoted.cfg"), "volume \"0.5\"").unwrap();
+    let output = csgocfg(&dir, &["patch", "--check", "no-eol.cfg", "quoted.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+
+    let output = csgocfg(&dir, &["merge", "base.cfg", "ours.cfg", "theirs.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    assert!(stdout(&output).contains("<<<<<<<"));
+
+    fs::write(dir.join("values.cfg"), "volume 2\n").unwrap();
+    let output = csgocfg(&dir, &["validate", "values.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    assert!(stderr(&output).contains("`volume` must be between 0 and 1"));
+
+    fs::write(dir.join("values.cfg"), "volum 1\n").unwrap();
+    let output = csgocfg(&dir, &["validate", "values.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    let output = csgocfg(&dir, &["validate", "--deny-warnings", "values.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+
+    fs::write(
+        dir.join("binds.cfg"),
+        "bind f +use\nbind f +reload\nunbindall\n",
+    )
+    .unwrap();
+    let output = csgocfg(&dir, &["lint", "binds.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    assert!(stderr(&output).contains("error[unbindall-after-binds]"));
+    let output = csgocfg(&dir, &["lint", "-A", "unbindall-after-binds", "binds.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert!(stderr(&output).contains("warning[duplicate-bind]"));
+    let output = csgocfg(
+        &dir,
+        &[
+            "lint",
+            "-D",
+            "all",
+            "-A",
+            "unbindall-after-binds",
+            "binds.cfg",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    let output = csgocfg(&dir, &["lint", "-A", "all", "binds.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    let output = csgocfg(&dir, &["lint", "-A", "no-such-rule", "binds.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::USAGE));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_stdin_and_stdout() {
+    let dir = temp_dir("pipeline");
+    fs::write(dir.join("team.cfg"), "volume 1\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_csgocfg"))
+        .args(["patch", "-", "team.cfg", "-o", "-"])
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"sensitivity 2\nvolume 0.5\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(stdout(&output), "sensitivity 2\nvolume 1\n");
+
+    let output = csgocfg(&dir, &["patch", "team.cfg", "team.cfg", "-o", "out.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(
+        fs::read_to_string(dir.join("out.cfg")).unwrap(),
+        "volume 1\n"
+    );
+
+    let output = csgocfg(&dir, &["validate", "-"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert!(stdout(&output).contains("`<stdin>` is valid"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_machine_readable_formats() {
+    let dir = temp_dir("formats");
+    fs::write(dir.join("binds.cfg"), "bind f +use\nbind mouse_4 +use\n").unwrap();
+    fs::write(dir.join("invalid.cfg"), "bind \"w\n").unwrap();
+
+    let output = csgocfg(&dir, &["validate", "--format", "json", "binds.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    let json = stdout(&output);
+    assert!(json.starts_with("[\n  {\"file\": "), "{}", json);
+    assert!(json.contains(
+        "\"line\": 2, \"column\": 6, \"end_line\": 2, \"end_column\": 13, \
+         \"severity\": \"error\", \"rule\": \"unknown-key\""
+    ));
+
+    let output = csgocfg(&dir, &["lint", "--format=json", "-A", "all", "binds.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(stdout(&output), "[]\n");
+
+    let output = csgocfg(&dir, &["validate", "--format", "sarif", "invalid.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::INVALID_CONFIG));
+    let sarif = stdout(&output);
+    assert!(sarif.contains("\"version\": \"2.1.0\""));
+    assert!(sarif.contains("\"ruleId\": \"invalid-string-literal\""));
+    assert!(sarif.contains("\"startLine\": 1, \"startColumn\": 6"));
+
+    let output = csgocfg(&dir, &["validate", "--format", "xml", "binds.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::USAGE));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_exec_graph() {
+    let dir = temp_dir("exec");
+    fs::create_dir(dir.join("binds")).unwrap();
+    fs::write(
+        dir.join("autoexec.cfg"),
+        "exec practice; exec binds/nades\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("practice.cfg"),
+        "sv_cheats 1\nexec binds\\nades.cfg\n",
+    )
+    .unwrap();
+    fs::write(dir.join("binds").join("nades.cfg"), "bind mouse4 +use\n").unwrap();
+
+    let output = csgocfg(&dir, &["validate", "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert!(stdout(&output).contains("as are the 2 config(s) it executes"));
+
+    fs::write(
+        dir.join("binds").join("nades.cfg"),
+        "volume 2\nexec autoexec\n",
+    )
+    .unwrap();
+    fs::write(dir.join("other.cfg"), "exec missing\n").unwrap();
+    let output = csgocfg(&dir, &["validate", "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    let errors = stderr(&output);
+    assert!(errors.contains("`volume` must be between 0 and 1"));
+    assert!(errors.contains(
+        "error[exec-cycle]: executing `autoexec.cfg` again forms a cycle \
+         (autoexec.cfg -> practice.cfg -> binds/nades.cfg -> autoexec.cfg)"
+    ));
+
+    let output = csgocfg(
+        &dir.join("binds"),
+        &["validate", "--root", "..", "../other.cfg"],
+    );
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    assert!(
+        stderr(&output).contains("cannot find `missing.cfg` to execute (other.cfg -> missing.cfg)")
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_restore() {
+    let dir = temp_dir("restore");
+    let config = dir.join("autoexec.cfg");
+    let read = || fs::read_to_string(&config).unwrap();
+    fs::write(&config, "volume 0.1\n").unwrap();
+    fs::write(dir.join("first.cfg"), "volume 0.2\n").unwrap();
+    fs::write(dir.join("second.cfg"), "volume 0.3\n").unwrap();
+
+    csgocfg(&dir, &["patch", "autoexec.cfg", "first.cfg"]);
+    csgocfg(&dir, &["patch", "autoexec.cfg", "second.cfg"]);
+
+    let output = csgocfg(&dir, &["restore", "--list", "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    let ids: Vec<String> = stdout(&output)
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap().to_owned())
+        .collect();
+    assert_eq!(ids.len(), 2);
+
+    // The latest backup wins, and restoring backs up what it replaces so it can be undone
+    let output = csgocfg(&dir, &["restore", "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(read(), "volume 0.2\n");
+    csgocfg(&dir, &["restore", "autoexec.cfg"]);
+    assert_eq!(read(), "volume 0.3\n");
+
+    let output = csgocfg(&dir, &["restore", "--to", &ids[0], "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(read(), "volume 0.1\n");
+
+    let output = csgocfg(
+        &dir,
+        &["restore", "--to", "20200101T000000Z", "autoexec.cfg"],
+    );
+    assert_eq!(output.status.code(), Some(exit_code::IO));
+    assert!(stderr(&output).contains("no backup `20200101T000000Z` found"));
+
+    // A config which was deleted can still be restored from its backups
+    fs::remove_file(&config).unwrap();
+    let output = csgocfg(&dir, &["restore", "autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(read(), "volume 0.3\n");
+
+    let output = csgocfg(&dir, &["restore", "missing/autoexec.cfg"]);
+    assert_eq!(output.status.code(), Some(exit_code::IO));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_explain() {
+    let dir = temp_dir("explain");
+    fs::write(
+        dir.join("base.cfg"),
+        "sensitivity 2\nsensitivity 1.5\nvolume 0.5\n",
+    )
+    .unwrap();
+    fs::write(dir.join("team.cfg"), "volume 0.3\nbind f +use\n").unwrap();
+    fs::write(dir.join("awp.cfg"), "bind f +lookatweapon\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_csgocfg"))
+        .args(["patch", "-", "team.cfg", "awp.cfg", "--explain", "-o", "-"])
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&fs::read(dir.join("base.cfg")).unwrap())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(
+        stdout(&output),
+        "sensitivity 2\nsensitivity 1.5\nvolume 0.3\nbind f +lookatweapon\n"
+    );
+
+    let team = dir.join("team.cfg").canonicalize().unwrap();
+    let awp = dir.join("awp.cfg").canonicalize().unwrap();
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "sensitivity 1.5  (<stdin>)\nvolume 0.3  ({})\nbind f +lookatweapon  ({})\n",
+            team.display(),
+            awp.display()
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_merge_driver() {
+    let dir = temp_dir("merge-driver");
+    let ours = dir.join(".merge_file_ours");
+    let read = || fs::read_to_string(&ours).unwrap();
+    let args = &[
+        "merge-driver",
+        ".merge_file_base",
+        ".merge_file_ours",
+        ".merge_file_theirs",
+    ];
+    fs::write(dir.join(".merge_file_base"), "volume 0.5\nsensitivity 2\n").unwrap();
+
+    fs::write(&ours, "volume 0.3\nsensitivity 2\n").unwrap();
+    fs::write(
+        dir.join(".merge_file_theirs"),
+        "volume 0.5\nsensitivity 1.5\n",
+    )
+    .unwrap();
+    let output = csgocfg(&dir, args);
+    assert_eq!(output.status.code(), Some(exit_code::SUCCESS));
+    assert_eq!(read(), "volume 0.3\nsensitivity 1.5\n");
+
+    fs::write(&ours, "volume 0.3\nsensitivity 2\n").unwrap();
+    fs::write(dir.join(".merge_file_theirs"), "volume 1\nsensitivity 2\n").unwrap();
+    let output = csgocfg(&dir, args);
+    assert_eq!(output.status.code(), Some(exit_code::FAILURE));
+    assert_eq!(
+        read(),
+        "sensitivity 2\n<<<<<<< ours\nvolume 0.3\n=======\nvolume 1\n>>>>>>> theirs\n"
+    );
+
+    // Git keeps whatever the driver left in ours, so a side which does not parse leaves both
+    // sides as a single conflict
+    fs::write(&ours, "volume 0.3\nsensitivity 2\n").unwrap();
+    fs::write(dir.join(".merge_file_theirs"), "bind \"w").unwrap();
+    let output = csgocfg(&dir, args);
+    assert_eq!(output.status.code(), Some(exit_code::INVALID_CONFIG));
+    assert_eq!(
+        read(),
+        "<<<<<<< ours\nvolume 0.3\nsensitivity 2\n=======\nbind \"w\n>>>>>>> theirs\n"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}